@@ -0,0 +1,128 @@
+//! Prints histograms of what a few generators produce at different sizes.
+//!
+//! Run it with `cargo run --example generators [size] [n]` to check a
+//! generator's distribution before pointing it at a slow remote runner.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use quickcheck::{sample, Arbitrary, Gen};
+use quickcheck_macros::Arbitrary;
+
+#[derive(Arbitrary, Debug, Clone)]
+struct SortArgs {
+    xs: Vec<isize>,
+    reverse: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Command {
+    Get,
+    Put(String),
+    Delete,
+}
+
+impl Arbitrary for Command {
+    fn arbitrary(g: &mut Gen) -> Self {
+        match g.choose(&[0, 1, 2]).unwrap() {
+            0 => Command::Get,
+            1 => Command::Put(String::arbitrary(g)),
+            _ => Command::Delete,
+        }
+    }
+}
+
+/// Prints one row per bucket, with its share of all samples and a bar.
+fn histogram<K: Ord + ToString>(title: &str, keys: impl Iterator<Item = K>) {
+    let mut counts = BTreeMap::new();
+    for key in keys {
+        *counts.entry(key).or_insert(0usize) += 1;
+    }
+    let total: usize = counts.values().sum();
+    println!("{} ({} samples)", title, total);
+    for (key, count) in counts {
+        let pct = 100.0 * count as f64 / total.max(1) as f64;
+        let bar = "#".repeat((pct / 2.0).round() as usize);
+        println!("  {:>22} {:5.1}% {}", key.to_string(), pct, bar);
+    }
+    println!();
+}
+
+/// Buckets a length into powers of two.
+fn length_bucket(len: usize) -> (usize, String) {
+    if len == 0 {
+        return (0, "0".to_string());
+    }
+    let lo = 1usize << (usize::BITS - 1 - len.leading_zeros());
+    (lo, format!("{}..{}", lo, lo * 2))
+}
+
+/// Buckets an integer by sign and number of decimal digits.
+fn magnitude_bucket(x: isize) -> (i32, String) {
+    let digits = x.unsigned_abs().checked_ilog10().map_or(0, |d| d + 1) as i32;
+    match x.signum() {
+        0 => (0, "0".to_string()),
+        1 => (digits, format!("+{} digits", digits)),
+        _ => (-digits, format!("-{} digits", digits)),
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let size = args.next().and_then(|s| s.parse().ok()).unwrap_or(100);
+    let n = args.next().and_then(|s| s.parse().ok()).unwrap_or(1000);
+    println!("Sampling {} values per generator at size {}\n", n, size);
+
+    let vecs = sample::<Vec<isize>>(size, n);
+    histogram(
+        "Vec<isize>: length",
+        vecs.iter().map(|xs| length_bucket(xs.len())).map(Keyed::from),
+    );
+    histogram(
+        "Vec<isize>: elements",
+        vecs.iter().flatten().map(|&x| magnitude_bucket(x)).map(Keyed::from),
+    );
+
+    let sorts = sample::<SortArgs>(size, n);
+    histogram(
+        "SortArgs (derived): xs length",
+        sorts.iter().map(|a| length_bucket(a.xs.len())).map(Keyed::from),
+    );
+    histogram("SortArgs (derived): reverse", sorts.iter().map(|a| a.reverse));
+
+    histogram(
+        "Option<u8>: variant",
+        sample::<Option<u8>>(size, n).into_iter().map(|x| {
+            if x.is_some() {
+                "Some"
+            } else {
+                "None"
+            }
+        }),
+    );
+    histogram(
+        "Command: variant",
+        sample::<Command>(size, n).into_iter().map(|c| match c {
+            Command::Get => "Get",
+            Command::Put(v) if v.is_empty() => "Put (empty value)",
+            Command::Put(_) => "Put",
+            Command::Delete => "Delete",
+        }),
+    );
+}
+
+/// A histogram key that sorts by its first field and prints its second.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Keyed<O: Ord>(O, String);
+
+impl<O: Ord> From<(O, String)> for Keyed<O> {
+    fn from((order, name): (O, String)) -> Self {
+        Keyed(order, name)
+    }
+}
+
+impl<O: Ord> std::fmt::Display for Keyed<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.1)
+    }
+}
@@ -0,0 +1,127 @@
+//! The fixtures of the conformance suite, for Rust runners.
+//!
+//! Runners in other languages implement the same four tests under the same
+//! names: an echo that returns its arguments, a test that always panics or
+//! throws with the message `conformance panic`, a test whose precondition
+//! rejects every case, and a test whose precondition always panics or throws
+//! with the message `conformance precondition panic`.
+
+use std::collections::BTreeMap;
+
+use quickcheck_runner::TestFunction;
+use serde::{Deserialize, Serialize};
+
+/// The test ID of the echo fixture.
+pub const ECHO: &str = "conformance_echo";
+/// The test ID of the panicking fixture.
+pub const PANIC: &str = "conformance_panic";
+/// The test ID of the discarding fixture.
+pub const DISCARD: &str = "conformance_discard";
+/// The test ID of the fixture whose precondition panics.
+pub const PRECONDITION_PANIC: &str = "conformance_precondition_panic";
+
+/// The arguments the suite sends to fixtures, covering the common shapes of
+/// MessagePack values.
+///
+/// Integers stay within 2^53, which JavaScript numbers hold exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub int: i64,
+    pub float: f64,
+    pub text: String,
+    pub flag: bool,
+    pub missing: Option<i64>,
+    pub list: Vec<i64>,
+    pub map: BTreeMap<String, Vec<String>>,
+    pub nested: Option<Box<Sample>>,
+}
+
+impl Sample {
+    /// The samples the echo fixture must return unchanged.
+    pub fn examples() -> Vec<Sample> {
+        let empty = Sample {
+            int: 0,
+            float: 0.0,
+            text: String::new(),
+            flag: false,
+            missing: None,
+            list: vec![],
+            map: BTreeMap::new(),
+            nested: None,
+        };
+        let full = Sample {
+            int: -(1 << 40),
+            float: -1.5,
+            text: "ü ✓ \"quoted\"\n".to_string(),
+            flag: true,
+            missing: None,
+            list: vec![1, -1, i32::MAX as i64, 1 << 52],
+            map: BTreeMap::from([
+                ("a".to_string(), vec!["x".to_string()]),
+                ("".to_string(), vec![]),
+            ]),
+            nested: Some(Box::new(empty.clone())),
+        };
+        vec![empty, full]
+    }
+}
+
+/// Returns its arguments unchanged.
+pub struct Echo;
+
+impl TestFunction for Echo {
+    type Args = Sample;
+    type Return = Sample;
+    const TEST_ID: &'static str = ECHO;
+
+    fn execute(&self, sample: Sample) -> Result<Sample, String> {
+        Ok(sample)
+    }
+}
+
+/// Always panics.
+pub struct Panic;
+
+impl TestFunction for Panic {
+    type Args = Sample;
+    type Return = ();
+    const TEST_ID: &'static str = PANIC;
+
+    fn execute(&self, _: Sample) -> Result<(), String> {
+        panic!("conformance panic")
+    }
+}
+
+/// Discards every case.
+pub struct Discard;
+
+impl TestFunction for Discard {
+    type Args = Sample;
+    type Return = ();
+    const TEST_ID: &'static str = DISCARD;
+
+    fn execute(&self, _: Sample) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn precondition(&self, _: &Sample) -> bool {
+        false
+    }
+}
+
+/// Panics in its precondition.
+pub struct PreconditionPanic;
+
+impl TestFunction for PreconditionPanic {
+    type Args = Sample;
+    type Return = ();
+    const TEST_ID: &'static str = PRECONDITION_PANIC;
+
+    fn execute(&self, _: Sample) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn precondition(&self, _: &Sample) -> bool {
+        panic!("conformance precondition panic")
+    }
+}
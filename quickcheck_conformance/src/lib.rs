@@ -0,0 +1,525 @@
+//! A conformance suite for runners.
+//!
+//! The suite talks to live runners over gRPC, whatever language they are
+//! written in, and checks that they follow the protocol the way the Rust
+//! reference runner does. Checks of how cases are reported need the runner
+//! to serve the fixtures described in `fixtures`; checks whose fixture is not
+//! given are skipped. Endpoints are connected to the way testers connect to
+//! them, so `unix:`, `stdio:` and `inproc:` endpoints work, and so do TLS and
+//! tokens. Run it from Rust with `Conformance::run`, or from the command line
+//! with
+//!
+//! ```text
+//! cargo run -p quickcheck_conformance -- http://127.0.0.1:50051 \
+//!     --echo http://127.0.0.1:50051 --panic http://127.0.0.1:50052 \
+//!     --discard http://127.0.0.1:50053 \
+//!     --precondition-panic http://127.0.0.1:50054
+//! ```
+
+use std::env;
+use std::fmt;
+
+use quickcheck_rpc::codec::Codec;
+use quickcheck_rpc::execute_response::TestStatus;
+use quickcheck_rpc::security::{SendToken, Tls};
+use quickcheck_rpc::test_runner_client::TestRunnerClient;
+use quickcheck_rpc::transport;
+use quickcheck_rpc::version::{self, PROTOCOL_VERSION};
+use quickcheck_rpc::{
+    ExecuteRequest, ExecuteResponse, HandshakeRequest, HandshakeResponse,
+};
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use tonic::Code;
+
+use crate::fixtures::{Sample, DISCARD, ECHO, PANIC, PRECONDITION_PANIC};
+
+pub mod fixtures;
+
+/// A conformance run against a runner, and the endpoints serving its
+/// fixtures.
+pub struct Conformance {
+    endpoint: String,
+    echo: Option<String>,
+    panic: Option<String>,
+    discard: Option<String>,
+    precondition_panic: Option<String>,
+    tls: Option<Tls>,
+    token: Option<String>,
+}
+
+impl Conformance {
+    /// Checks the runner at `endpoint`, which can serve any test.
+    pub fn new<S: Into<String>>(endpoint: S) -> Conformance {
+        Conformance {
+            endpoint: endpoint.into(),
+            echo: None,
+            panic: None,
+            discard: None,
+            precondition_panic: None,
+            tls: None,
+            token: env::var("QUICKCHECK_TOKEN").ok(),
+        }
+    }
+
+    /// The endpoint of the runner serving the
+    /// `conformance_precondition_panic` fixture.
+    pub fn precondition_panic<S: Into<String>>(
+        mut self,
+        endpoint: S,
+    ) -> Conformance {
+        self.precondition_panic = Some(endpoint.into());
+        self
+    }
+
+    /// The TLS settings used to connect to runners at `https` endpoints.
+    pub fn tls(mut self, tls: Tls) -> Conformance {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// The shared secret sent to runners with every request. The default is
+    /// the value of the `QUICKCHECK_TOKEN` environment variable, if it is
+    /// set.
+    pub fn token<S: Into<String>>(mut self, secret: S) -> Conformance {
+        self.token = Some(secret.into());
+        self
+    }
+
+    /// The endpoint of the runner serving the `conformance_echo` fixture.
+    pub fn echo<S: Into<String>>(mut self, endpoint: S) -> Conformance {
+        self.echo = Some(endpoint.into());
+        self
+    }
+
+    /// The endpoint of the runner serving the `conformance_panic` fixture.
+    pub fn panic<S: Into<String>>(mut self, endpoint: S) -> Conformance {
+        self.panic = Some(endpoint.into());
+        self
+    }
+
+    /// The endpoint of the runner serving the `conformance_discard` fixture.
+    pub fn discard<S: Into<String>>(mut self, endpoint: S) -> Conformance {
+        self.discard = Some(endpoint.into());
+        self
+    }
+
+    /// Runs every check, and reports all of their results.
+    pub async fn run(&self) -> Report {
+        let mut report = Report {
+            endpoint: self.endpoint.clone(),
+            checks: vec![],
+            skipped: vec![],
+        };
+        let mut client = match self.connect(&self.endpoint).await {
+            Ok(client) => client,
+            Err(e) => {
+                report.push("connect", Err(e));
+                return report;
+            }
+        };
+        report.push("handshake", handshake(&mut client).await);
+        report.push(
+            "handshake_with_older_tester",
+            handshake_with_older_tester(&mut client).await,
+        );
+        report.push(
+            "handshake_without_common_version",
+            handshake_without_common_version(&mut client).await,
+        );
+        report.push("unknown_test_id", unknown_test_id(&mut client).await);
+
+        let checks = ["malformed_arguments", "return_values_round_trip"];
+        let fixture = self.fixture(&mut report, &self.echo, &checks).await;
+        if let Some(mut client) = fixture {
+            report.push(checks[0], malformed_arguments(&mut client).await);
+            report
+                .push(checks[1], return_values_round_trip(&mut client).await);
+        }
+        let checks = ["failures_are_reported"];
+        let fixture = self.fixture(&mut report, &self.panic, &checks).await;
+        if let Some(mut client) = fixture {
+            report.push(checks[0], failures_are_reported(&mut client).await);
+        }
+        let checks = ["discards_are_reported"];
+        let fixture = self.fixture(&mut report, &self.discard, &checks).await;
+        if let Some(mut client) = fixture {
+            report.push(checks[0], discards_are_reported(&mut client).await);
+        }
+        let checks = ["precondition_failures_are_reported"];
+        let fixture =
+            self.fixture(&mut report, &self.precondition_panic, &checks).await;
+        if let Some(mut client) = fixture {
+            let result = precondition_failures_are_reported(&mut client).await;
+            report.push(checks[0], result);
+        }
+        report
+    }
+
+    /// Connects to the runner serving a fixture, failing or skipping its
+    /// checks if it cannot.
+    async fn fixture(
+        &self,
+        report: &mut Report,
+        endpoint: &Option<String>,
+        checks: &[&'static str],
+    ) -> Option<Client> {
+        let Some(endpoint) = endpoint else {
+            report.skipped.extend(checks);
+            return None;
+        };
+        match self.connect(endpoint).await {
+            Ok(client) => Some(client),
+            Err(e) => {
+                for check in checks {
+                    report.push(check, Err(e.clone()));
+                }
+                None
+            }
+        }
+    }
+
+    async fn connect(&self, endpoint: &str) -> Result<Client, String> {
+        let channel =
+            transport::channel(endpoint, self.tls.as_ref()).await.map_err(
+                |e| format!("Failed to connect to {}: {}", endpoint, e),
+            )?;
+        let token = SendToken::new(self.token.as_deref())?;
+        Ok(TestRunnerClient::with_interceptor(channel, token))
+    }
+}
+
+/// The results of a conformance run.
+#[derive(Debug)]
+pub struct Report {
+    pub endpoint: String,
+    pub checks: Vec<Check>,
+    /// The checks that were not run, because their fixture was not given.
+    pub skipped: Vec<&'static str>,
+}
+
+/// The result of one check.
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub result: Result<(), String>,
+}
+
+impl Report {
+    fn push(&mut self, name: &'static str, result: Result<(), String>) {
+        self.checks.push(Check { name, result });
+    }
+
+    /// Returns `true` if every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Conformance of the runner at {}:", self.endpoint)?;
+        for check in &self.checks {
+            match &check.result {
+                Ok(()) => writeln!(f, "  ok    {}", check.name)?,
+                Err(e) => writeln!(f, "  FAIL  {}: {}", check.name, e)?,
+            }
+        }
+        for name in &self.skipped {
+            writeln!(f, "  skip  {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+type Client = TestRunnerClient<InterceptedService<Channel, SendToken>>;
+
+async fn execute(
+    client: &mut Client,
+    test_id: &str,
+    test_data: Vec<u8>,
+) -> Result<ExecuteResponse, tonic::Status> {
+    let request = ExecuteRequest {
+        test_id: test_id.to_string(),
+        test_data,
+        ..ExecuteRequest::default()
+    };
+    client.execute(request).await.map(|response| response.into_inner())
+}
+
+fn encode(sample: &Sample) -> Vec<u8> {
+    Codec::MsgPack.encode(sample).unwrap()
+}
+
+/// Checks that a call failed with `code`.
+fn expect_error(
+    result: Result<ExecuteResponse, tonic::Status>,
+    code: Code,
+) -> Result<(), String> {
+    match result {
+        Err(status) if status.code() == code => Ok(()),
+        Err(status) => Err(format!(
+            "Expected {:?}, found {:?}: {}",
+            code,
+            status.code(),
+            status.message()
+        )),
+        Ok(response) => Err(format!(
+            "Expected {:?}, found a response: {:?}",
+            code, response
+        )),
+    }
+}
+
+/// Checks that a call returned a response with `status`.
+fn expect_status(
+    result: Result<ExecuteResponse, tonic::Status>,
+    status: TestStatus,
+) -> Result<ExecuteResponse, String> {
+    match result {
+        Ok(response) if response.status() == status => Ok(response),
+        Ok(response) => Err(format!(
+            "Expected {:?}, found {:?}: {:?}",
+            status,
+            response.status(),
+            response.failure_detail
+        )),
+        Err(status) => Err(format!("Execute failed: {}", status)),
+    }
+}
+
+async fn call_handshake(
+    client: &mut Client,
+    min_version: u32,
+    max_version: u32,
+) -> Result<HandshakeResponse, String> {
+    let request = HandshakeRequest { min_version, max_version };
+    match client.handshake(request).await {
+        Ok(response) => Ok(response.into_inner()),
+        Err(status) => Err(format!("Handshake failed: {}", status)),
+    }
+}
+
+/// The runner answers the tester's versions with the highest common one.
+async fn handshake(client: &mut Client) -> Result<(), String> {
+    let request = version::request();
+    let response =
+        call_handshake(client, request.min_version, request.max_version)
+            .await?;
+    if response.min_version > response.max_version {
+        return Err(format!(
+            "Runner speaks protocol versions {} to {}, which is empty",
+            response.min_version, response.max_version
+        ));
+    }
+    if response.implementation.is_empty() {
+        return Err("Runner does not name its implementation".to_string());
+    }
+    version::accept(&response).map(|_| ())
+}
+
+/// The runner still negotiates with testers that speak only version 1, if
+/// it speaks that version itself.
+async fn handshake_with_older_tester(
+    client: &mut Client,
+) -> Result<(), String> {
+    let response = call_handshake(client, 1, 1).await?;
+    let expected = version::negotiate(
+        (1, 1),
+        (response.min_version, response.max_version),
+    )
+    .unwrap_or(0);
+    if response.version != expected {
+        return Err(format!(
+            "Runner chose protocol version {} for a tester speaking 1 to 1, \
+             instead of {}",
+            response.version, expected
+        ));
+    }
+    Ok(())
+}
+
+/// The runner answers a tester it shares no version with by choosing version
+/// 0, instead of failing the call.
+async fn handshake_without_common_version(
+    client: &mut Client,
+) -> Result<(), String> {
+    let future = PROTOCOL_VERSION.max(1_000);
+    let response = call_handshake(client, future, future + 1).await?;
+    if response.max_version >= future {
+        // The runner is from the future, and this check cannot tell.
+        return Ok(());
+    }
+    if response.version != 0 {
+        return Err(format!(
+            "Runner chose protocol version {} for a tester speaking {} to {}",
+            response.version,
+            future,
+            future + 1
+        ));
+    }
+    Ok(())
+}
+
+/// Tests the runner doesn't have are rejected with `NOT_FOUND`.
+async fn unknown_test_id(client: &mut Client) -> Result<(), String> {
+    let sample = &Sample::examples()[0];
+    let result =
+        execute(client, "conformance_no_such_test", encode(sample)).await;
+    expect_error(result, Code::NotFound)
+}
+
+/// Arguments that are not valid MessagePack are rejected with
+/// `INVALID_ARGUMENT`, rather than reported as a failing case.
+async fn malformed_arguments(client: &mut Client) -> Result<(), String> {
+    // 0xc1 is never used by MessagePack.
+    let result = execute(client, ECHO, vec![0xc1]).await;
+    expect_error(result, Code::InvalidArgument)
+}
+
+/// Return values come back encoded like the arguments, and decode to the
+/// same values.
+async fn return_values_round_trip(client: &mut Client) -> Result<(), String> {
+    for sample in Sample::examples() {
+        let result = execute(client, ECHO, encode(&sample)).await;
+        let response = expect_status(result, TestStatus::Passed)?;
+        let return_value =
+            response.return_value.ok_or("Echo returned no value")?;
+        let echoed: Sample = Codec::MsgPack
+            .decode(&return_value)
+            .map_err(|e| format!("Failed to decode the echo: {}", e))?;
+        if echoed != sample {
+            return Err(format!("Sent {:?}, got back {:?}", sample, echoed));
+        }
+    }
+    Ok(())
+}
+
+/// Panics and exceptions fail the case, with their message as its detail.
+async fn failures_are_reported(client: &mut Client) -> Result<(), String> {
+    expect_failure(client, PANIC, "conformance panic").await
+}
+
+/// Panics and exceptions in a precondition fail the case too, rather than
+/// the call.
+async fn precondition_failures_are_reported(
+    client: &mut Client,
+) -> Result<(), String> {
+    let message = "conformance precondition panic";
+    expect_failure(client, PRECONDITION_PANIC, message).await
+}
+
+/// Checks that a fixture fails with a detail containing `message`.
+async fn expect_failure(
+    client: &mut Client,
+    test_id: &str,
+    message: &str,
+) -> Result<(), String> {
+    let sample = &Sample::examples()[0];
+    let result = execute(client, test_id, encode(sample)).await;
+    let response = expect_status(result, TestStatus::Failed)?;
+    match response.failure_detail {
+        Some(detail) if detail.contains(message) => Ok(()),
+        detail => Err(format!(
+            "Expected a detail containing {:?}, found {:?}",
+            message, detail
+        )),
+    }
+}
+
+/// Cases whose precondition does not hold are reported as `INVALID_INPUT`.
+async fn discards_are_reported(client: &mut Client) -> Result<(), String> {
+    let sample = &Sample::examples()[0];
+    let result = execute(client, DISCARD, encode(sample)).await;
+    expect_status(result, TestStatus::InvalidInput).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use quickcheck_runner::SingleTestRunner;
+
+    use super::fixtures::{Discard, Echo, Panic, PreconditionPanic};
+    use super::Conformance;
+
+    #[tokio::test]
+    async fn the_reference_runner_conforms() {
+        let address = "127.0.0.1:0";
+        let echo = SingleTestRunner::new(Echo).spawn(address).await.unwrap();
+        let panic = SingleTestRunner::new(Panic).spawn(address).await.unwrap();
+        let discard =
+            SingleTestRunner::new(Discard).spawn(address).await.unwrap();
+        let precondition_panic = SingleTestRunner::new(PreconditionPanic)
+            .spawn(address)
+            .await
+            .unwrap();
+
+        let report = Conformance::new(&echo)
+            .echo(&echo)
+            .panic(panic)
+            .discard(discard)
+            .precondition_panic(precondition_panic)
+            .run()
+            .await;
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 9);
+        assert!(report.skipped.is_empty());
+
+        // The echo runner doesn't discard, and checks without a fixture
+        // are skipped.
+        let report = Conformance::new(&echo).discard(&echo).run().await;
+        assert!(!report.passed());
+        let failed: Vec<_> = report
+            .checks
+            .iter()
+            .filter(|check| check.result.is_err())
+            .map(|check| check.name)
+            .collect();
+        assert_eq!(failed, vec!["discards_are_reported"]);
+        assert_eq!(
+            report.skipped,
+            vec![
+                "malformed_arguments",
+                "return_values_round_trip",
+                "failures_are_reported",
+                "precondition_failures_are_reported"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn runners_are_reached_like_testers_reach_them() {
+        let echo = SingleTestRunner::new(Echo)
+            .token("s3cret")
+            .spawn("inproc:conformance_echo")
+            .await
+            .unwrap();
+        let report =
+            Conformance::new(&echo).echo(&echo).token("s3cret").run().await;
+        assert!(report.passed(), "{}", report);
+
+        let report = Conformance::new(&echo).run().await;
+        let handshake = &report.checks[0];
+        assert_eq!(handshake.name, "handshake");
+        let error = handshake.result.as_ref().unwrap_err();
+        assert!(error.contains("Missing token"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn unreachable_runners_fail() {
+        let report = Conformance::new("http://127.0.0.1:1").run().await;
+        assert!(!report.passed());
+        assert_eq!(report.checks[0].name, "connect");
+    }
+
+    /// The Node.js runner keeps its own copy of the proto, which must not
+    /// drift from this one.
+    #[test]
+    fn proto_copies_match() {
+        let rust =
+            include_str!("../../quickcheck_rpc/proto/pbt_service.proto");
+        let node = include_str!(
+            "../../quickcheck_runner_nodejs/proto/pbt_service.proto"
+        );
+        assert!(rust == node, "the proto copies differ");
+    }
+}
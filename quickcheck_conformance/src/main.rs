@@ -0,0 +1,75 @@
+use std::env;
+use std::process;
+
+use quickcheck_conformance::Conformance;
+use quickcheck_rpc::security::Tls;
+
+const USAGE: &str =
+    "usage: quickcheck_conformance ENDPOINT [--echo ENDPOINT] \
+     [--panic ENDPOINT] [--discard ENDPOINT] \
+     [--precondition-panic ENDPOINT] [--token SECRET] \
+     [--ca-cert PATH] [--cert PATH --key PATH] \
+     [--domain-name NAME]";
+
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let Some(endpoint) = args.next() else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+    let mut conformance = Conformance::new(endpoint);
+    let mut tls = None::<Tls>;
+    let (mut cert, mut key) = (None, None);
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        };
+        conformance = match flag.as_str() {
+            "--echo" => conformance.echo(value),
+            "--panic" => conformance.panic(value),
+            "--discard" => conformance.discard(value),
+            "--precondition-panic" => conformance.precondition_panic(value),
+            "--token" => conformance.token(value),
+            "--ca-cert" => {
+                tls = Some(tls.unwrap_or_default().ca_cert(value));
+                conformance
+            }
+            "--domain-name" => {
+                tls = Some(tls.unwrap_or_default().domain_name(value));
+                conformance
+            }
+            "--cert" => {
+                cert = Some(value);
+                conformance
+            }
+            "--key" => {
+                key = Some(value);
+                conformance
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        };
+    }
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            tls = Some(tls.unwrap_or_default().identity(cert, key));
+        }
+        (None, None) => {}
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+    if let Some(tls) = tls {
+        conformance = conformance.tls(tls);
+    }
+    let report = conformance.run().await;
+    print!("{}", report);
+    if !report.passed() {
+        process::exit(1);
+    }
+}
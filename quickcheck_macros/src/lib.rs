@@ -150,7 +150,7 @@ fn gen_arbitrary_body(data: &Data) -> proc_macro2::TokenStream {
                 Fields::Unnamed(fields) => {
                     // For `struct Foo(u32, bool)`
                     // generates: `Self(u32::arbitrary(g), bool::arbitrary(g))`
-                    let recurse = fields.unnamed.iter().enumerate().map(|(_i, f)| {
+                    let recurse = fields.unnamed.iter().map(|f| {
                         let ty = &f.ty;
                         quote! {
                             <#ty as ::quickcheck::Arbitrary>::arbitrary(g)
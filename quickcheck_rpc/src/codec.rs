@@ -0,0 +1,156 @@
+//! The encodings of arguments and return values on the wire.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::Encoding;
+
+/// How arguments are encoded in `ExecuteRequest::test_data`, and return
+/// values in `ExecuteResponse::return_value`.
+///
+/// The encoding travels with every request, and runners encode the return
+/// value of a case the same way as its arguments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    /// MessagePack, with structs encoded as maps keyed by field name.
+    #[default]
+    MsgPack,
+    /// MessagePack, with structs encoded as arrays of their fields. Smaller,
+    /// but both sides must agree on the order of the fields.
+    MsgPackCompact,
+    /// JSON, which every language can read and which is easy to inspect.
+    /// Maps must have string keys, and floats must be finite.
+    Json,
+    /// CBOR.
+    Cbor,
+}
+
+impl Codec {
+    /// Encodes a value.
+    pub fn encode<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            Codec::MsgPack => {
+                rmp_serde::to_vec_named(value).map_err(|e| e.to_string())
+            }
+            Codec::MsgPackCompact => {
+                rmp_serde::to_vec(value).map_err(|e| e.to_string())
+            }
+            Codec::Json => {
+                serde_json::to_vec(value).map_err(|e| e.to_string())
+            }
+            Codec::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(value, &mut bytes)
+                    .map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decodes a value.
+    pub fn decode<T: DeserializeOwned>(
+        self,
+        bytes: &[u8],
+    ) -> Result<T, String> {
+        match self {
+            Codec::MsgPack | Codec::MsgPackCompact => {
+                rmp_serde::from_slice(bytes).map_err(|e| e.to_string())
+            }
+            Codec::Json => {
+                serde_json::from_slice(bytes).map_err(|e| e.to_string())
+            }
+            Codec::Cbor => {
+                ciborium::from_reader(bytes).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// The encoding to put in requests encoded with this codec.
+    pub fn encoding(self) -> Encoding {
+        match self {
+            Codec::MsgPack => Encoding::Msgpack,
+            Codec::MsgPackCompact => Encoding::MsgpackCompact,
+            Codec::Json => Encoding::Json,
+            Codec::Cbor => Encoding::Cbor,
+        }
+    }
+}
+
+impl From<Encoding> for Codec {
+    fn from(encoding: Encoding) -> Codec {
+        match encoding {
+            Encoding::Msgpack => Codec::MsgPack,
+            Encoding::MsgpackCompact => Codec::MsgPackCompact,
+            Encoding::Json => Codec::Json,
+            Encoding::Cbor => Codec::Cbor,
+        }
+    }
+}
+
+impl TryFrom<i32> for Codec {
+    type Error = String;
+
+    /// Reads the `encoding` field of a request, rejecting encodings added to
+    /// the protocol after this codec was written.
+    fn try_from(encoding: i32) -> Result<Codec, String> {
+        Encoding::try_from(encoding)
+            .map(Codec::from)
+            .map_err(|_| format!("Unsupported encoding {}", encoding))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::Codec;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Dot,
+        Circle { radius: f64 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Args {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, Option<u8>>,
+        data: (i64, bool),
+    }
+
+    const CODECS: [Codec; 4] =
+        [Codec::MsgPack, Codec::MsgPackCompact, Codec::Json, Codec::Cbor];
+
+    #[test]
+    fn round_trips() {
+        let args = Args {
+            name: "ü".to_string(),
+            shapes: vec![Shape::Dot, Shape::Circle { radius: 1.5 }],
+            tags: BTreeMap::from([("a".to_string(), None)]),
+            data: (-3, true),
+        };
+        for codec in CODECS {
+            let bytes = codec.encode(&args).unwrap();
+            assert_eq!(codec.decode::<Args>(&bytes).unwrap(), args);
+            assert_eq!(Codec::from(codec.encoding()), codec);
+        }
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(Codec::Json.encode(&(1, "a")).unwrap(), br#"[1,"a"]"#);
+        let named = Codec::MsgPack.encode(&Shape::Circle { radius: 0.0 });
+        let compact =
+            Codec::MsgPackCompact.encode(&Shape::Circle { radius: 0.0 });
+        assert!(named.unwrap().len() > compact.unwrap().len());
+        assert!(Codec::Json.decode::<u8>(b"256").is_err());
+        assert_eq!(Codec::try_from(2), Ok(Codec::Json));
+        assert!(Codec::try_from(42).is_err());
+    }
+}
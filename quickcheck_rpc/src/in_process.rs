@@ -0,0 +1,23 @@
+//! Runners served inside the tester's own process.
+//!
+//! A runner serving on `inproc:NAME` registers a channel to itself here under
+//! `NAME`, and testers given the endpoint `inproc:NAME` look it up. Calls
+//! still go through gRPC, over in-memory pipes instead of sockets.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use tonic::transport::Channel;
+
+static RUNNERS: Mutex<BTreeMap<String, Channel>> = Mutex::new(BTreeMap::new());
+
+/// Registers the channel to an in-process runner, replacing any runner
+/// registered under the same name.
+pub fn register(name: &str, channel: Channel) {
+    RUNNERS.lock().unwrap().insert(name.to_string(), channel);
+}
+
+/// The channel to the in-process runner registered under `name`.
+pub fn channel(name: &str) -> Option<Channel> {
+    RUNNERS.lock().unwrap().get(name).cloned()
+}
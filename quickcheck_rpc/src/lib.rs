@@ -3,3 +3,9 @@
 // }
 mod pbt_service;
 pub use pbt_service::*;
+pub mod codec;
+pub mod in_process;
+pub mod schema;
+pub mod security;
+pub mod transport;
+pub mod version;
@@ -1,25 +1,218 @@
 // This file is @generated by prost-build.
+/// 协商协议版本的请求
+/// 协议版本 1 是引入 Handshake 之前的协议，只有 Execute
+/// 版本 2 增加了 Handshake、ExecuteBatch、Run、Generate、Shrink、Describe、会话，
+/// 以及 ExecuteRequest 和 ExecuteResponse 的 version 字段
+/// 新增 RPC 或字段时提高版本号，tester 只对协商出的版本支持的 RPC 发起调用
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct HandshakeRequest {
+    /// tester 支持的最低协议版本
+    #[prost(uint32, tag = "1")]
+    pub min_version: u32,
+    /// tester 支持的最高协议版本
+    #[prost(uint32, tag = "2")]
+    pub max_version: u32,
+}
+/// 协商协议版本的响应
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct HandshakeResponse {
+    /// 协商出的版本，即双方都支持的最高版本；没有共同版本时为 0
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    /// runner 支持的最低协议版本
+    #[prost(uint32, tag = "2")]
+    pub min_version: u32,
+    /// runner 支持的最高协议版本
+    #[prost(uint32, tag = "3")]
+    pub max_version: u32,
+    /// runner 的实现及其版本，例如 "quickcheck_runner 0.1.0"，用于错误报告
+    #[prost(string, tag = "4")]
+    pub implementation: ::prost::alloc::string::String,
+}
 /// 测试执行的请求
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ExecuteRequest {
     /// 要测试的属性的唯一标识符，例如 "property_reverse_list"
     #[prost(string, tag = "1")]
     pub test_id: ::prost::alloc::string::String,
-    /// MsgPack
+    /// 按 encoding 编码的参数
     #[prost(bytes = "vec", tag = "2")]
     pub test_data: ::prost::alloc::vec::Vec<u8>,
+    /// 所属会话的 ID，由 BeginSession 返回；为空表示不属于任何会话
+    #[prost(string, tag = "3")]
+    pub session_id: ::prost::alloc::string::String,
+    /// test_data 的编码；runner 用同样的编码返回 return_value
+    #[prost(enumeration = "Encoding", tag = "4")]
+    pub encoding: i32,
+    /// tester 发送本请求所用的协议版本，即 Handshake 协商出的版本
+    /// 为 0 表示 tester 早于此字段，按版本 1 处理
+    /// runner 不支持该版本时以 FAILED_PRECONDITION 拒绝调用，无需先调用 Handshake
+    #[prost(uint32, tag = "5")]
+    pub version: u32,
 }
-/// 测试执行的响应
+/// 批量执行的请求
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExecuteBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub requests: ::prost::alloc::vec::Vec<ExecuteRequest>,
+}
+/// 批量执行的响应
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExecuteBatchResponse {
+    /// 与 requests 一一对应
+    #[prost(message, repeated, tag = "1")]
+    pub responses: ::prost::alloc::vec::Vec<ExecuteResponse>,
+}
+/// Run 流中 tester 发送的消息
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct RunRequest {
+    /// 用例在本流中的编号，由 tester 分配，结果中原样返回
+    #[prost(uint64, tag = "1")]
+    pub case_id: u64,
+    /// 要执行的用例
+    #[prost(message, optional, tag = "2")]
+    pub case: ::core::option::Option<ExecuteRequest>,
+}
+/// Run 流中 runner 发送的消息
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RunResponse {
+    #[prost(oneof = "run_response::Event", tags = "1, 2")]
+    pub event: ::core::option::Option<run_response::Event>,
+}
+/// Nested message and enum types in `RunResponse`.
+pub mod run_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        /// 一个用例的结果
+        #[prost(message, tag = "1")]
+        Result(super::CaseResult),
+        /// 执行用例期间输出的日志或进度信息
+        #[prost(message, tag = "2")]
+        Log(super::RunLog),
+    }
+}
+/// Run 流中一个用例的结果
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CaseResult {
+    #[prost(uint64, tag = "1")]
+    pub case_id: u64,
+    /// 用例的执行结果；请求无效时为空
+    #[prost(message, optional, tag = "2")]
+    pub response: ::core::option::Option<ExecuteResponse>,
+    /// 请求无效时的错误信息，例如测试 ID 或会话不存在
+    #[prost(string, optional, tag = "3")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Run 流中的一条日志
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct RunLog {
+    /// 输出日志的用例
+    #[prost(uint64, tag = "1")]
+    pub case_id: u64,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// 生成参数的请求
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct GenerateRequest {
+    #[prost(string, tag = "1")]
+    pub test_id: ::prost::alloc::string::String,
+    /// 随机种子；相同的种子和规模必须生成相同的参数
+    #[prost(uint64, tag = "2")]
+    pub seed: u64,
+    /// 生成规模，含义与 QuickCheck 的 Gen::size 相同
+    #[prost(uint64, tag = "3")]
+    pub size: u64,
+}
+/// 生成参数的响应
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct GenerateResponse {
+    /// 生成的参数，MsgPack 编码，可直接作为 ExecuteRequest 的 test_data
+    #[prost(bytes = "vec", tag = "1")]
+    pub test_data: ::prost::alloc::vec::Vec<u8>,
+}
+/// 收缩参数的请求
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ShrinkRequest {
+    #[prost(string, tag = "1")]
+    pub test_id: ::prost::alloc::string::String,
+    /// 失败用例的参数，MsgPack 编码
+    #[prost(bytes = "vec", tag = "2")]
+    pub test_data: ::prost::alloc::vec::Vec<u8>,
+}
+/// 收缩参数的响应
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ShrinkResponse {
+    /// 更小的候选参数，MsgPack 编码，最有希望的排在前面
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub candidates: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
+/// 查询 schema 的请求
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DescribeRequest {
+    #[prost(string, tag = "1")]
+    pub test_id: ::prost::alloc::string::String,
+}
+/// 查询 schema 的响应
+/// schema 是 JSON Schema 的子集（type、title、properties、required、
+/// additionalProperties、items、prefixItems、oneOf、const），
+/// 描述 MsgPack 编码后的值，本身也以 MsgPack 编码
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DescribeResponse {
+    /// 参数的 schema
+    #[prost(bytes = "vec", tag = "1")]
+    pub args_schema: ::prost::alloc::vec::Vec<u8>,
+    /// 返回值的 schema；未提供时不检查返回值
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub return_schema: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
+/// 开启会话的请求
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct BeginSessionRequest {
+    /// 会话所针对的属性的唯一标识符
+    #[prost(string, tag = "1")]
+    pub test_id: ::prost::alloc::string::String,
+}
+/// 开启会话的响应
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct BeginSessionResponse {
+    /// 新会话的 ID，在后续的 ExecuteRequest 和 EndSessionRequest 中使用
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+}
+/// 结束会话的请求
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EndSessionRequest {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+}
+/// 结束会话的响应
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EndSessionResponse {}
+/// 测试执行的响应
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecuteResponse {
     #[prost(enumeration = "execute_response::TestStatus", tag = "1")]
     pub status: i32,
     /// 如果测试失败，这里包含详细信息，例如断言错误、堆栈跟踪等。
     #[prost(string, optional, tag = "2")]
     pub failure_detail: ::core::option::Option<::prost::alloc::string::String>,
-    /// 测试函数的返回值，序列化为MsgPack，用于复合测试比较
+    /// 测试函数的返回值，按请求的 encoding 编码，用于复合测试比较
     #[prost(bytes = "vec", optional, tag = "3")]
     pub return_value: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// 对本次用例的分类标签，用于覆盖率统计（对应 QuickCheck 的 label/classify）
+    #[prost(string, repeated, tag = "4")]
+    pub labels: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// 本次用例向各个统计表添加的值（对应 QuickCheck 的 tabulate）
+    #[prost(message, repeated, tag = "5")]
+    pub tables: ::prost::alloc::vec::Vec<TableEntry>,
+    /// 本次用例声明的覆盖率要求（对应 QuickCheck 的 cover）
+    #[prost(message, repeated, tag = "6")]
+    pub coverage: ::prost::alloc::vec::Vec<CoverRequirement>,
+    /// runner 处理本请求所用的协议版本，与请求的 version 一致
+    /// 为 0 表示 runner 早于此字段
+    #[prost(uint32, tag = "7")]
+    pub version: u32,
 }
 /// Nested message and enum types in `ExecuteResponse`.
 pub mod execute_response {
@@ -67,6 +260,63 @@ pub mod execute_response {
         }
     }
 }
+/// 统计表中的一个值
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct TableEntry {
+    /// 统计表的名称
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    /// 添加到统计表中的值
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
+/// 覆盖率要求：至少 percentage% 的通过用例必须带有 label
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CoverRequirement {
+    /// 要求的标签
+    #[prost(string, tag = "1")]
+    pub label: ::prost::alloc::string::String,
+    /// 至少要带有该标签的通过用例百分比
+    #[prost(double, tag = "2")]
+    pub percentage: f64,
+}
+/// 参数和返回值的编码
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Encoding {
+    /// MsgPack，结构体编码为以字段名为键的 map
+    Msgpack = 0,
+    /// MsgPack，结构体编码为按字段顺序排列的数组
+    MsgpackCompact = 1,
+    /// JSON（UTF-8）
+    Json = 2,
+    /// CBOR
+    Cbor = 3,
+}
+impl Encoding {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Msgpack => "MSGPACK",
+            Self::MsgpackCompact => "MSGPACK_COMPACT",
+            Self::Json => "JSON",
+            Self::Cbor => "CBOR",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "MSGPACK" => Some(Self::Msgpack),
+            "MSGPACK_COMPACT" => Some(Self::MsgpackCompact),
+            "JSON" => Some(Self::Json),
+            "CBOR" => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod test_runner_client {
     #![allow(
@@ -159,6 +409,32 @@ pub mod test_runner_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
+        /// 协商协议版本，tester 在每次运行中首次连接 runner 时调用
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，视为协议版本 1
+        pub async fn handshake(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HandshakeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HandshakeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/Handshake",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "Handshake"));
+            self.inner.unary(req, path, codec).await
+        }
         /// 执行一个属性测试
         /// Orchestrator 调用此方法
         pub async fn execute(
@@ -185,6 +461,184 @@ pub mod test_runner_client {
                 .insert(GrpcMethod::new("pbt_service.TestRunner", "Execute"));
             self.inner.unary(req, path, codec).await
         }
+        /// 按顺序执行一批用例，每个请求对应一个响应，顺序一致
+        /// 用于减少廉价属性的往返延迟
+        pub async fn execute_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExecuteBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExecuteBatchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/ExecuteBatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "ExecuteBatch"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// 可选：在一个长期存在的双向流上执行整个测试运行
+        /// tester 依次发送用例和收缩候选，runner 按顺序返回结果，并可随时发送日志
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，tester 退回到 Execute
+        pub async fn run(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::RunRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::RunResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/Run",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "Run"));
+            self.inner.streaming(req, path, codec).await
+        }
+        /// 可选：由 runner 生成参数，用于参数类型在 runner 的语言中定义的属性
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED
+        pub async fn generate(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GenerateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GenerateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/Generate",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "Generate"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// 可选：由 runner 收缩失败用例的参数，与 Generate 配合使用
+        pub async fn shrink(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ShrinkRequest>,
+        ) -> std::result::Result<tonic::Response<super::ShrinkResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/Shrink",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "Shrink"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// 可选：返回测试参数和返回值的 schema，tester 在运行前据此检查类型是否兼容
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，tester 跳过检查
+        pub async fn describe(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DescribeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DescribeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/Describe",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "Describe"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// 开启一个会话，会话内的多次 Execute 调用共享 Runner 端的状态
+        /// 用于状态机测试（例如键值存储、缓存、队列）
+        pub async fn begin_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BeginSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BeginSessionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/BeginSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "BeginSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// 结束一个会话，Runner 释放该会话的状态
+        pub async fn end_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::EndSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::EndSessionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/pbt_service.TestRunner/EndSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("pbt_service.TestRunner", "EndSession"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -200,12 +654,83 @@ pub mod test_runner_server {
     /// Generated trait containing gRPC methods that should be implemented for use with TestRunnerServer.
     #[async_trait]
     pub trait TestRunner: std::marker::Send + std::marker::Sync + 'static {
+        /// 协商协议版本，tester 在每次运行中首次连接 runner 时调用
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，视为协议版本 1
+        async fn handshake(
+            &self,
+            request: tonic::Request<super::HandshakeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HandshakeResponse>,
+            tonic::Status,
+        >;
         /// 执行一个属性测试
         /// Orchestrator 调用此方法
         async fn execute(
             &self,
             request: tonic::Request<super::ExecuteRequest>,
         ) -> std::result::Result<tonic::Response<super::ExecuteResponse>, tonic::Status>;
+        /// 按顺序执行一批用例，每个请求对应一个响应，顺序一致
+        /// 用于减少廉价属性的往返延迟
+        async fn execute_batch(
+            &self,
+            request: tonic::Request<super::ExecuteBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExecuteBatchResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the Run method.
+        type RunStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::RunResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// 可选：在一个长期存在的双向流上执行整个测试运行
+        /// tester 依次发送用例和收缩候选，runner 按顺序返回结果，并可随时发送日志
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，tester 退回到 Execute
+        async fn run(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::RunRequest>>,
+        ) -> std::result::Result<tonic::Response<Self::RunStream>, tonic::Status>;
+        /// 可选：由 runner 生成参数，用于参数类型在 runner 的语言中定义的属性
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED
+        async fn generate(
+            &self,
+            request: tonic::Request<super::GenerateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GenerateResponse>,
+            tonic::Status,
+        >;
+        /// 可选：由 runner 收缩失败用例的参数，与 Generate 配合使用
+        async fn shrink(
+            &self,
+            request: tonic::Request<super::ShrinkRequest>,
+        ) -> std::result::Result<tonic::Response<super::ShrinkResponse>, tonic::Status>;
+        /// 可选：返回测试参数和返回值的 schema，tester 在运行前据此检查类型是否兼容
+        /// 不支持此方法的 runner 返回 UNIMPLEMENTED，tester 跳过检查
+        async fn describe(
+            &self,
+            request: tonic::Request<super::DescribeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DescribeResponse>,
+            tonic::Status,
+        >;
+        /// 开启一个会话，会话内的多次 Execute 调用共享 Runner 端的状态
+        /// 用于状态机测试（例如键值存储、缓存、队列）
+        async fn begin_session(
+            &self,
+            request: tonic::Request<super::BeginSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BeginSessionResponse>,
+            tonic::Status,
+        >;
+        /// 结束一个会话，Runner 释放该会话的状态
+        async fn end_session(
+            &self,
+            request: tonic::Request<super::EndSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::EndSessionResponse>,
+            tonic::Status,
+        >;
     }
     /// 主服务，由各个语言的 Test Stub 实现
     #[derive(Debug)]
@@ -284,25 +809,25 @@ pub mod test_runner_server {
         }
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             match req.uri().path() {
-                "/pbt_service.TestRunner/Execute" => {
+                "/pbt_service.TestRunner/Handshake" => {
                     #[allow(non_camel_case_types)]
-                    struct ExecuteSvc<T: TestRunner>(pub Arc<T>);
+                    struct HandshakeSvc<T: TestRunner>(pub Arc<T>);
                     impl<
                         T: TestRunner,
-                    > tonic::server::UnaryService<super::ExecuteRequest>
-                    for ExecuteSvc<T> {
-                        type Response = super::ExecuteResponse;
+                    > tonic::server::UnaryService<super::HandshakeRequest>
+                    for HandshakeSvc<T> {
+                        type Response = super::HandshakeResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ExecuteRequest>,
+                            request: tonic::Request<super::HandshakeRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as TestRunner>::execute(&inner, request).await
+                                <T as TestRunner>::handshake(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -313,7 +838,365 @@ pub mod test_runner_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = ExecuteSvc(inner);
+                        let method = HandshakeSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/Execute" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecuteSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::ExecuteRequest>
+                    for ExecuteSvc<T> {
+                        type Response = super::ExecuteResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExecuteRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::execute(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ExecuteSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/ExecuteBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecuteBatchSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::ExecuteBatchRequest>
+                    for ExecuteBatchSvc<T> {
+                        type Response = super::ExecuteBatchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExecuteBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::execute_batch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ExecuteBatchSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/Run" => {
+                    #[allow(non_camel_case_types)]
+                    struct RunSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::StreamingService<super::RunRequest> for RunSvc<T> {
+                        type Response = super::RunResponse;
+                        type ResponseStream = T::RunStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::RunRequest>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::run(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RunSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/Generate" => {
+                    #[allow(non_camel_case_types)]
+                    struct GenerateSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::GenerateRequest>
+                    for GenerateSvc<T> {
+                        type Response = super::GenerateResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GenerateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::generate(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GenerateSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/Shrink" => {
+                    #[allow(non_camel_case_types)]
+                    struct ShrinkSvc<T: TestRunner>(pub Arc<T>);
+                    impl<T: TestRunner> tonic::server::UnaryService<super::ShrinkRequest>
+                    for ShrinkSvc<T> {
+                        type Response = super::ShrinkResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ShrinkRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::shrink(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ShrinkSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/Describe" => {
+                    #[allow(non_camel_case_types)]
+                    struct DescribeSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::DescribeRequest>
+                    for DescribeSvc<T> {
+                        type Response = super::DescribeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DescribeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::describe(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DescribeSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/BeginSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct BeginSessionSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::BeginSessionRequest>
+                    for BeginSessionSvc<T> {
+                        type Response = super::BeginSessionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BeginSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::begin_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = BeginSessionSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/pbt_service.TestRunner/EndSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct EndSessionSvc<T: TestRunner>(pub Arc<T>);
+                    impl<
+                        T: TestRunner,
+                    > tonic::server::UnaryService<super::EndSessionRequest>
+                    for EndSessionSvc<T> {
+                        type Response = super::EndSessionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EndSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as TestRunner>::end_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EndSessionSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
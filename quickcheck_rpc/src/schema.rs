@@ -0,0 +1,1064 @@
+//! Schemas describing the arguments and return values exchanged with a
+//! runner.
+//!
+//! A `Schema` is a subset of JSON Schema describing the MessagePack encoding
+//! of a value: structs are objects, sequences and tuples are arrays, and enums
+//! are externally tagged. Schemas travel in `DescribeResponse` encoded with
+//! MessagePack, so runners in any language can publish one as a plain object.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess,
+    IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Serialize};
+
+/// The shape of a value, as a subset of JSON Schema.
+///
+/// A schema without a type, alternatives or constant accepts any value.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    /// One of `null`, `boolean`, `integer`, `number`, `string`, `array` and
+    /// `object`.
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub kind: Option<String>,
+    /// The name of the type, for reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The fields of an object.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Schema>,
+    /// The fields an object must have.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    /// The values of an object used as a map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_properties: Option<Box<Schema>>,
+    /// The elements of an array of any length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Schema>>,
+    /// The elements of an array of fixed length, such as a tuple.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefix_items: Vec<Schema>,
+    /// Alternatives, such as the variants of an enum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub one_of: Vec<Schema>,
+    /// The only string accepted, such as the name of a unit variant.
+    #[serde(
+        rename = "const",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub constant: Option<String>,
+}
+
+impl Schema {
+    /// A schema of the given type.
+    pub fn of(kind: &str) -> Schema {
+        Schema { kind: Some(kind.to_string()), ..Schema::default() }
+    }
+
+    /// A schema accepting any value, standing for the named type where it
+    /// contains itself.
+    fn named(name: &str) -> Schema {
+        Schema { title: Some(name.to_string()), ..Schema::default() }
+    }
+
+    fn nullable(schema: Schema) -> Schema {
+        Schema {
+            one_of: vec![Schema::of("null"), schema],
+            ..Schema::default()
+        }
+    }
+
+    /// Returns `true` if this schema accepts any value.
+    fn is_any(&self) -> bool {
+        self.kind.is_none()
+            && self.one_of.is_empty()
+            && self.constant.is_none()
+    }
+
+    /// Returns `true` if this schema accepts `null`, so that a missing field
+    /// of this schema is accepted too.
+    fn is_nullable(&self) -> bool {
+        self.is_any()
+            || self.kind.as_deref() == Some("null")
+            || self.one_of.iter().any(Schema::is_nullable)
+    }
+
+    fn describe(&self) -> String {
+        if let Some(constant) = &self.constant {
+            return format!("{:?}", constant);
+        }
+        match (&self.title, &self.kind) {
+            (Some(title), _) => title.clone(),
+            (None, Some(kind)) => kind.clone(),
+            (None, None) if !self.one_of.is_empty() => {
+                let alternatives: Vec<_> =
+                    self.one_of.iter().map(Schema::describe).collect();
+                alternatives.join(" | ")
+            }
+            (None, None) => "any value".to_string(),
+        }
+    }
+}
+
+/// Derives the schema of a type from its `Deserialize` implementation.
+///
+/// The type is traced by deserializing it from a stand-in that records what
+/// the implementation asks for, as many times as it takes to visit every enum
+/// variant. Where a type contains itself, the inner occurrence accepts any
+/// value. Types whose implementation relies on `deserialize_any`, such as
+/// untagged enums, or that reject the stand-in values get a schema accepting
+/// any value.
+pub fn schema_of<T: DeserializeOwned>() -> Schema {
+    let mut plan = Plan::default();
+    for _ in 0..MAX_PASSES {
+        plan.changed = false;
+        let mut schema = Schema::default();
+        let tracer = Tracer::new(&mut plan, String::new(), &mut schema);
+        match T::deserialize(tracer) {
+            Ok(_) if plan.is_complete() && !plan.changed => return schema,
+            Ok(_) | Err(TraceError::Restart) => {}
+            Err(_) => return Schema::default(),
+        }
+    }
+    Schema::default()
+}
+
+/// The most times a type is traced before giving up.
+const MAX_PASSES: usize = 256;
+
+/// Returns every way in which values written with the `sent` schema may fail
+/// to be read with the `expected` schema, one line per mismatch.
+///
+/// Fields the reader does not know are not reported, since serde ignores
+/// them, but missing fields are unless they may be null.
+pub fn mismatches(sent: &Schema, expected: &Schema) -> Vec<String> {
+    let mut found = vec![];
+    compare(sent, expected, "", &mut found);
+    found
+}
+
+fn compare(
+    sent: &Schema,
+    expected: &Schema,
+    path: &str,
+    found: &mut Vec<String>,
+) {
+    if sent.is_any() || expected.is_any() {
+        return;
+    }
+    let at = if path.is_empty() {
+        "top level".to_string()
+    } else {
+        format!("`{}`", path)
+    };
+    let mismatch = || {
+        format!(
+            "at {}: expected {}, found {}",
+            at,
+            expected.describe(),
+            sent.describe()
+        )
+    };
+    if !sent.one_of.is_empty() {
+        for alternative in &sent.one_of {
+            compare(alternative, expected, path, found);
+        }
+        return;
+    }
+    if !expected.one_of.is_empty() {
+        let accepted = expected
+            .one_of
+            .iter()
+            .any(|alternative| mismatches_at(sent, alternative, path) == 0);
+        if !accepted {
+            found.push(mismatch());
+        }
+        return;
+    }
+    let kinds = (sent.kind.as_deref(), expected.kind.as_deref());
+    let same_kind =
+        kinds.0 == kinds.1 || kinds == (Some("integer"), Some("number"));
+    if !same_kind
+        || (expected.constant.is_some() && sent.constant != expected.constant)
+    {
+        found.push(mismatch());
+        return;
+    }
+    for field in &expected.required {
+        let nullable =
+            expected.properties.get(field).is_some_and(Schema::is_nullable);
+        if !sent.properties.contains_key(field) && !nullable {
+            found.push(format!("at {}: missing field `{}`", at, field));
+        }
+    }
+    for (field, schema) in &sent.properties {
+        if let Some(expected) = expected.properties.get(field) {
+            compare(schema, expected, &join(path, field), found);
+        }
+    }
+    if let (Some(sent), Some(expected)) =
+        (&sent.additional_properties, &expected.additional_properties)
+    {
+        compare(sent, expected, &format!("{}[_]", path), found);
+    }
+    if let (Some(sent), Some(expected)) = (&sent.items, &expected.items) {
+        compare(sent, expected, &format!("{}[]", path), found);
+    }
+    if sent.prefix_items.len() != expected.prefix_items.len() {
+        found.push(format!(
+            "at {}: expected {} elements, found {}",
+            at,
+            expected.prefix_items.len(),
+            sent.prefix_items.len()
+        ));
+        return;
+    }
+    let elements = sent.prefix_items.iter().zip(&expected.prefix_items);
+    for (i, (sent, expected)) in elements.enumerate() {
+        compare(sent, expected, &join(path, &i.to_string()), found);
+    }
+}
+
+fn mismatches_at(sent: &Schema, expected: &Schema, path: &str) -> usize {
+    let mut found = vec![];
+    compare(sent, expected, path, &mut found);
+    found.len()
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+/// What is known about a type across the passes tracing it.
+#[derive(Default)]
+struct Plan {
+    /// The variants of every enum seen, traced or not.
+    enums: HashMap<&'static str, Vec<Option<Schema>>>,
+    /// The first variant traced of every enum, which does not contain the
+    /// enum itself and so stands in for it where it does.
+    base: HashMap<&'static str, usize>,
+    /// Enum variants that led back into their own enum before its base
+    /// variant was known.
+    failed: HashSet<(&'static str, usize)>,
+    /// How many times each enum has been traced, to take turns between its
+    /// variants once all of them are known.
+    visits: HashMap<&'static str, usize>,
+    /// Containers left empty because their elements lead back into an
+    /// enclosing type, by path, with the name of that type.
+    cuts: HashMap<String, &'static str>,
+    /// The named types being traced.
+    stack: Vec<&'static str>,
+    /// How many stand-ins for recursive enums are being traced. Their
+    /// containers are left empty and nothing about them is recorded.
+    shallow: usize,
+    /// Whether this pass learnt anything new.
+    changed: bool,
+}
+
+impl Plan {
+    fn is_complete(&self) -> bool {
+        self.enums
+            .values()
+            .all(|variants| variants.iter().all(Option::is_some))
+    }
+
+    /// Chooses which variant of an enum to trace next.
+    fn choose(&mut self, name: &'static str) -> Option<usize> {
+        let variants = &self.enums[name];
+        let has_base = self.base.contains_key(name);
+        let untraced = (0..variants.len()).find(|&i| {
+            variants[i].is_none()
+                && (has_base || !self.failed.contains(&(name, i)))
+        });
+        if untraced.is_some() {
+            return untraced;
+        }
+        if !has_base {
+            return None;
+        }
+        let visits = self.visits.entry(name).or_default();
+        *visits += 1;
+        Some(*visits % variants.len())
+    }
+
+    /// Returns the schema standing in for the elements of a container if it
+    /// is left empty.
+    fn skipped(&self, path: &str) -> Option<Schema> {
+        if self.shallow > 0 {
+            return Some(Schema::default());
+        }
+        self.cuts.get(path).map(|name| Schema::named(name))
+    }
+
+    /// Records that the elements of a container lead back into an enclosing
+    /// type, so that it is left empty from now on.
+    fn cut(&mut self, path: String, e: TraceError) -> TraceError {
+        match e {
+            TraceError::Recursive(name) => {
+                self.cuts.insert(path, name);
+                self.changed = true;
+                TraceError::Restart
+            }
+            e => e,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum TraceError {
+    /// An enum was reached again while tracing itself, before any of its
+    /// variants was known.
+    Recursive(&'static str),
+    /// The plan changed, so the type must be traced again.
+    Restart,
+    Custom(String),
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Recursive(name) => {
+                write!(f, "recursive type {}", name)
+            }
+            TraceError::Restart => write!(f, "restart"),
+            TraceError::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        TraceError::Custom(msg.to_string())
+    }
+}
+
+/// A deserializer that records the schema of what is deserialized from it,
+/// handing out stand-in values.
+struct Tracer<'a> {
+    plan: &'a mut Plan,
+    path: String,
+    schema: &'a mut Schema,
+}
+
+impl<'a> Tracer<'a> {
+    fn new(plan: &'a mut Plan, path: String, schema: &'a mut Schema) -> Self {
+        Tracer { plan, path, schema }
+    }
+
+    /// Traces a struct, keeping track of the structs being traced.
+    ///
+    /// A struct reached again while tracing itself is traced once more with
+    /// its containers left empty if an enum in between can stand in for it,
+    /// and otherwise reports the recursion so that a container in between is
+    /// left empty from then on.
+    fn named<T>(
+        self,
+        name: &'static str,
+        trace: impl FnOnce(Tracer<'_>) -> Result<T, TraceError>,
+    ) -> Result<T, TraceError> {
+        let plan = self.plan;
+        let Some(outer) = plan.stack.iter().position(|t| *t == name) else {
+            plan.stack.push(name);
+            let value = trace(Tracer::new(plan, self.path, self.schema));
+            plan.stack.pop();
+            return value;
+        };
+        if plan.shallow > 0 {
+            return Err(TraceError::Restart);
+        }
+        if !plan.stack[outer..].iter().any(|t| plan.base.contains_key(t)) {
+            return Err(TraceError::Recursive(name));
+        }
+        *self.schema = Schema::named(name);
+        plan.shallow += 1;
+        let value =
+            trace(Tracer::new(plan, self.path, &mut Schema::default()));
+        plan.shallow -= 1;
+        value
+    }
+
+    /// Traces the elements of a tuple, a tuple struct or a tuple variant.
+    fn elements<'de, V: Visitor<'de>>(
+        self,
+        title: Option<&str>,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let mut elements = vec![];
+        let value = visitor.visit_seq(Elements {
+            plan: self.plan,
+            path: &self.path,
+            len,
+            elements: &mut elements,
+        })?;
+        *self.schema = Schema {
+            title: title.map(str::to_string),
+            prefix_items: elements,
+            ..Schema::of("array")
+        };
+        Ok(value)
+    }
+
+    /// Traces the fields of a struct or a struct variant.
+    fn fields<'de, V: Visitor<'de>>(
+        self,
+        title: Option<&str>,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let mut schema = Schema {
+            title: title.map(str::to_string),
+            ..Schema::of("object")
+        };
+        let value = visitor.visit_map(Fields {
+            plan: self.plan,
+            path: &self.path,
+            fields,
+            schema: &mut schema,
+        })?;
+        *self.schema = schema;
+        Ok(value)
+    }
+}
+
+/// Traces one value with a seed, recording its schema.
+fn trace<'de, T: DeserializeSeed<'de>>(
+    seed: T,
+    plan: &mut Plan,
+    path: String,
+    schema: &mut Schema,
+) -> Result<T::Value, TraceError> {
+    seed.deserialize(Tracer::new(plan, path, schema))
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $kind:literal, $visit:ident($($value:expr)?);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                visitor: V,
+            ) -> Result<V::Value, TraceError> {
+                *self.schema = Schema::of($kind);
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Tracer<'_> {
+    type Error = TraceError;
+
+    trace_primitive! {
+        deserialize_bool => "boolean", visit_bool(false);
+        deserialize_i8 => "integer", visit_i8(1);
+        deserialize_i16 => "integer", visit_i16(1);
+        deserialize_i32 => "integer", visit_i32(1);
+        deserialize_i64 => "integer", visit_i64(1);
+        deserialize_i128 => "integer", visit_i128(1);
+        deserialize_u8 => "integer", visit_u8(1);
+        deserialize_u16 => "integer", visit_u16(1);
+        deserialize_u32 => "integer", visit_u32(1);
+        deserialize_u64 => "integer", visit_u64(1);
+        deserialize_u128 => "integer", visit_u128(1);
+        deserialize_f32 => "number", visit_f32(0.0);
+        deserialize_f64 => "number", visit_f64(0.0);
+        deserialize_char => "string", visit_char('a');
+        deserialize_str => "string", visit_str("");
+        deserialize_string => "string", visit_str("");
+        deserialize_identifier => "string", visit_str("");
+        deserialize_unit => "null", visit_unit();
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        *self.schema = Schema {
+            items: Some(Box::new(Schema::of("integer"))),
+            ..Schema::of("array")
+        };
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let path = format!("{}?", self.path);
+        if let Some(inner) = self.plan.skipped(&path) {
+            *self.schema = Schema::nullable(inner);
+            return visitor.visit_none();
+        }
+        let mut inner = Schema::default();
+        let tracer = Tracer::new(self.plan, path.clone(), &mut inner);
+        let value =
+            visitor.visit_some(tracer).map_err(|e| self.plan.cut(path, e))?;
+        *self.schema = Schema::nullable(inner);
+        Ok(value)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        // Unit structs are encoded as empty arrays.
+        *self.schema = Schema::of("array");
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.named(name, |tracer| visitor.visit_newtype_struct(tracer))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let mut items = Schema::default();
+        let path = format!("{}[]", self.path);
+        let value = visitor.visit_seq(Items {
+            plan: self.plan,
+            path: Some(path),
+            items: &mut items,
+        })?;
+        *self.schema =
+            Schema { items: Some(Box::new(items)), ..Schema::of("array") };
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.elements(None, len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.named(name, |tracer| tracer.elements(Some(name), len, visitor))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let mut values = Schema::default();
+        let path = format!("{}[_]", self.path);
+        let value = visitor.visit_map(Entries {
+            plan: self.plan,
+            path: Some(path),
+            values: &mut values,
+        })?;
+        *self.schema = Schema {
+            additional_properties: Some(Box::new(values)),
+            ..Schema::of("object")
+        };
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.named(name, |tracer| tracer.fields(Some(name), fields, visitor))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let plan = self.plan;
+        if !plan.enums.contains_key(name) {
+            plan.enums.insert(name, vec![None; variants.len()]);
+            plan.changed = true;
+        }
+        if plan.shallow > 0 || plan.stack.contains(&name) {
+            // Stand in for the enum with its base variant, which does not
+            // lead back into it.
+            let Some(&base) = plan.base.get(name) else {
+                return Err(if plan.shallow > 0 {
+                    TraceError::Restart
+                } else {
+                    TraceError::Recursive(name)
+                });
+            };
+            *self.schema = Schema::named(name);
+            plan.shallow += 1;
+            let value = visitor.visit_enum(Variant {
+                plan: &mut *plan,
+                path: self.path,
+                name: variants[base],
+                schema: &mut Schema::default(),
+            });
+            plan.shallow -= 1;
+            return value;
+        }
+        let Some(i) = plan.choose(name) else {
+            return Err(de::Error::custom(format!(
+                "no variant of {} can be traced",
+                name
+            )));
+        };
+        let mut variant = Schema::default();
+        plan.stack.push(name);
+        let value = visitor.visit_enum(Variant {
+            plan: &mut *plan,
+            path: self.path,
+            name: variants[i],
+            schema: &mut variant,
+        });
+        plan.stack.pop();
+        let value = match value {
+            Ok(value) => value,
+            Err(TraceError::Recursive(_)) if !plan.base.contains_key(name) => {
+                plan.failed.insert((name, i));
+                plan.changed = true;
+                return Err(TraceError::Restart);
+            }
+            Err(e) => return Err(e),
+        };
+        let known = &mut plan.enums.get_mut(name).unwrap()[i];
+        if known.is_none() {
+            *known = Some(variant);
+            plan.changed = true;
+        }
+        if !plan.base.contains_key(name) {
+            plan.base.insert(name, i);
+            plan.changed = true;
+        }
+        *self.schema = Schema {
+            title: Some(name.to_string()),
+            one_of: plan.enums[name].iter().flatten().cloned().collect(),
+            ..Schema::default()
+        };
+        Ok(value)
+    }
+}
+
+/// The elements of a sequence: one, so that its type is traced, or none if
+/// the sequence leads back into an enclosing type.
+struct Items<'a> {
+    plan: &'a mut Plan,
+    path: Option<String>,
+    items: &'a mut Schema,
+}
+
+impl<'de> SeqAccess<'de> for Items<'_> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TraceError> {
+        let Some(path) = self.path.take() else {
+            return Ok(None);
+        };
+        if let Some(items) = self.plan.skipped(&path) {
+            *self.items = items;
+            return Ok(None);
+        }
+        let value = trace(seed, self.plan, path.clone(), self.items)
+            .map_err(|e| self.plan.cut(path, e))?;
+        Ok(Some(value))
+    }
+}
+
+/// The elements of a tuple, each traced.
+struct Elements<'a> {
+    plan: &'a mut Plan,
+    path: &'a str,
+    len: usize,
+    elements: &'a mut Vec<Schema>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TraceError> {
+        let i = self.elements.len();
+        if i == self.len {
+            return Ok(None);
+        }
+        let mut element = Schema::default();
+        let path = join(self.path, &i.to_string());
+        let value = trace(seed, self.plan, path, &mut element)?;
+        self.elements.push(element);
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.elements.len())
+    }
+}
+
+/// The entries of a map: one, so that its types are traced, or none if the
+/// map leads back into an enclosing type.
+struct Entries<'a> {
+    plan: &'a mut Plan,
+    path: Option<String>,
+    values: &'a mut Schema,
+}
+
+impl<'de> MapAccess<'de> for Entries<'_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        let Some(path) = self.path.clone() else {
+            return Ok(None);
+        };
+        if let Some(values) = self.plan.skipped(&path) {
+            *self.values = values;
+            return Ok(None);
+        }
+        let key = trace(seed, self.plan, path, &mut Schema::default())?;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let path = self.path.take().unwrap_or_default();
+        trace(seed, self.plan, path.clone(), self.values)
+            .map_err(|e| self.plan.cut(path, e))
+    }
+}
+
+/// The fields of a struct, each traced.
+struct Fields<'a> {
+    plan: &'a mut Plan,
+    path: &'a str,
+    fields: &'static [&'static str],
+    schema: &'a mut Schema,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        match self.fields.get(self.schema.properties.len()) {
+            Some(field) => {
+                seed.deserialize(field.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let field = self.fields[self.schema.properties.len()];
+        let mut schema = Schema::default();
+        let value =
+            trace(seed, self.plan, join(self.path, field), &mut schema)?;
+        if !schema.is_nullable() {
+            self.schema.required.push(field.to_string());
+        }
+        self.schema.properties.insert(field.to_string(), schema);
+        Ok(value)
+    }
+}
+
+/// The chosen variant of an enum.
+struct Variant<'a> {
+    plan: &'a mut Plan,
+    path: String,
+    name: &'static str,
+    schema: &'a mut Schema,
+}
+
+impl<'a, 'de> EnumAccess<'de> for Variant<'a> {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self), TraceError> {
+        let value = seed.deserialize(self.name.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl Variant<'_> {
+    /// Records a variant with content, which is encoded as a map from the
+    /// name of the variant to its content.
+    fn content<T>(
+        self,
+        trace: impl FnOnce(Tracer<'_>) -> Result<T, TraceError>,
+    ) -> Result<T, TraceError> {
+        let mut content = Schema::default();
+        let path = join(&self.path, self.name);
+        let value = trace(Tracer::new(self.plan, path, &mut content))?;
+        *self.schema = Schema {
+            properties: BTreeMap::from([(self.name.to_string(), content)]),
+            required: vec![self.name.to_string()],
+            ..Schema::of("object")
+        };
+        Ok(value)
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_> {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), TraceError> {
+        *self.schema = Schema {
+            constant: Some(self.name.to_string()),
+            ..Schema::of("string")
+        };
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, TraceError> {
+        self.content(|tracer| seed.deserialize(tracer))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.content(|tracer| tracer.elements(None, len, visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.content(|tracer| tracer.fields(None, fields, visitor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::{Schema, mismatches, schema_of};
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Point {
+        x: i64,
+        y: f64,
+        label: Option<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    enum Shape {
+        Empty,
+        Dot(Point),
+        Line(Point, Point),
+        Polygon { points: Vec<Point> },
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    enum Tree {
+        Node(Box<Tree>, u8, Box<Tree>),
+        Leaf,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Dir {
+        name: String,
+        children: Vec<Dir>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Expr {
+        op: Op,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    enum Op {
+        Neg(Box<Expr>),
+        Const(BTreeMap<String, i32>),
+    }
+
+    fn object(fields: &[(&str, Schema)]) -> Schema {
+        let properties: BTreeMap<_, _> = fields
+            .iter()
+            .map(|(field, schema)| (field.to_string(), schema.clone()))
+            .collect();
+        Schema {
+            required: properties.keys().cloned().collect(),
+            properties,
+            ..Schema::of("object")
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let schema = schema_of::<Point>();
+        assert_eq!(schema.title.as_deref(), Some("Point"));
+        assert_eq!(schema.required, vec!["x", "y"]);
+        assert_eq!(schema.properties["x"], Schema::of("integer"));
+        assert_eq!(schema.properties["y"], Schema::of("number"));
+        assert_eq!(
+            schema.properties["label"].one_of,
+            vec![Schema::of("null"), Schema::of("string")]
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let schema = schema_of::<Shape>();
+        let variants: Vec<_> = schema
+            .one_of
+            .iter()
+            .map(|variant| match &variant.constant {
+                Some(name) => name.clone(),
+                None => variant.required.join(""),
+            })
+            .collect();
+        assert_eq!(variants, vec!["Empty", "Dot", "Line", "Polygon"]);
+        let line = &schema.one_of[2].properties["Line"];
+        assert_eq!(line.prefix_items.len(), 2);
+        let polygon = &schema.one_of[3].properties["Polygon"];
+        assert_eq!(polygon.required, vec!["points"]);
+        let points = polygon.properties["points"].items.as_ref().unwrap();
+        assert_eq!(points.title.as_deref(), Some("Point"));
+    }
+
+    #[test]
+    fn recursive_types() {
+        let tree = schema_of::<Tree>();
+        assert_eq!(tree.one_of.len(), 2);
+        let node = &tree.one_of[0].properties["Node"];
+        assert_eq!(node.prefix_items[0], Schema::named("Tree"));
+        assert_eq!(node.prefix_items[1], Schema::of("integer"));
+
+        let dir = schema_of::<Dir>();
+        let children = dir.properties["children"].items.as_ref().unwrap();
+        assert_eq!(**children, Schema::named("Dir"));
+
+        let expr = schema_of::<Expr>();
+        let op = &expr.properties["op"];
+        assert_eq!(op.one_of[0].properties["Neg"], Schema::named("Expr"));
+        let constants = &op.one_of[1].properties["Const"];
+        assert_eq!(
+            constants.additional_properties.as_deref(),
+            Some(&Schema::of("integer"))
+        );
+    }
+
+    #[test]
+    fn compatible_schemas() {
+        let point = schema_of::<Point>();
+        assert!(mismatches(&point, &point).is_empty());
+        assert!(
+            mismatches(&schema_of::<Shape>(), &schema_of::<Shape>())
+                .is_empty()
+        );
+        assert!(
+            mismatches(&Schema::of("integer"), &Schema::of("number"))
+                .is_empty()
+        );
+        let extra = object(&[
+            ("x", Schema::of("integer")),
+            ("y", Schema::of("integer")),
+            ("z", Schema::of("string")),
+        ]);
+        assert!(mismatches(&extra, &point).is_empty());
+        assert!(
+            mismatches(&schema_of::<u8>(), &schema_of::<Option<u32>>())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn incompatible_schemas() {
+        let sent = object(&[
+            ("x", Schema::of("string")),
+            ("label", Schema::of("integer")),
+        ]);
+        assert_eq!(
+            mismatches(&sent, &schema_of::<Point>()),
+            vec![
+                "at top level: missing field `y`",
+                "at `label`: expected null | string, found integer",
+                "at `x`: expected integer, found string",
+            ]
+        );
+        assert_eq!(
+            mismatches(&schema_of::<(u8, u8)>(), &schema_of::<(u8, u8, u8)>()),
+            vec!["at top level: expected 3 elements, found 2"]
+        );
+        assert_eq!(
+            mismatches(&schema_of::<Option<u8>>(), &schema_of::<u8>()),
+            vec!["at top level: expected integer, found null"]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! TLS and shared-secret authentication of runner connections.
+
+use std::path::Path;
+
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::transport::{
+    Certificate, ClientTlsConfig, Identity, ServerTlsConfig,
+};
+use tonic::{Request, Status};
+
+/// The metadata key that carries the shared secret, as `Bearer SECRET`.
+pub const TOKEN_KEY: &str = "authorization";
+
+/// TLS settings, with certificates and keys loaded from PEM files.
+///
+/// The files are read once, when they are set. An unreadable file is
+/// reported by the first configuration built from the settings.
+///
+/// A runner needs an identity, and requires clients to present a
+/// certificate signed by its CA certificate if it has one (mutual TLS). A
+/// tester checks the runner's certificate against its CA certificate, or the
+/// system's roots without one, and presents its identity if it has one.
+#[derive(Clone, Debug, Default)]
+pub struct Tls {
+    identity: Option<(Pem, Pem)>,
+    ca_cert: Option<Pem>,
+    domain_name: Option<String>,
+}
+
+impl Tls {
+    pub fn new() -> Tls {
+        Tls::default()
+    }
+
+    /// The certificate and private key to present to the other side.
+    pub fn identity<C, K>(mut self, cert: C, key: K) -> Tls
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        self.identity = Some((read(cert.as_ref()), read(key.as_ref())));
+        self
+    }
+
+    /// The certificate of the CA that signed the other side's certificate.
+    pub fn ca_cert<P: AsRef<Path>>(mut self, path: P) -> Tls {
+        self.ca_cert = Some(read(path.as_ref()));
+        self
+    }
+
+    /// The name a tester checks the runner's certificate against, instead
+    /// of the host of its endpoint.
+    pub fn domain_name<S: Into<String>>(mut self, name: S) -> Tls {
+        self.domain_name = Some(name.into());
+        self
+    }
+
+    /// The TLS configuration of a tester.
+    pub fn client_config(&self) -> Result<ClientTlsConfig, String> {
+        let mut config = ClientTlsConfig::new();
+        if let Some(pem) = &self.ca_cert {
+            let ca = Certificate::from_pem(pem.clone()?);
+            config = config.ca_certificate(ca);
+        } else {
+            config = config.with_enabled_roots();
+        }
+        if let Some(identity) = self.read_identity()? {
+            config = config.identity(identity);
+        }
+        if let Some(name) = &self.domain_name {
+            config = config.domain_name(name);
+        }
+        Ok(config)
+    }
+
+    /// The TLS configuration of a runner.
+    pub fn server_config(&self) -> Result<ServerTlsConfig, String> {
+        let identity = self
+            .read_identity()?
+            .ok_or("A runner needs a certificate and key to serve TLS")?;
+        let mut config = ServerTlsConfig::new().identity(identity);
+        if let Some(pem) = &self.ca_cert {
+            let ca = Certificate::from_pem(pem.clone()?);
+            config = config.client_ca_root(ca);
+        }
+        Ok(config)
+    }
+
+    fn read_identity(&self) -> Result<Option<Identity>, String> {
+        match &self.identity {
+            Some((cert, key)) => {
+                Ok(Some(Identity::from_pem(cert.clone()?, key.clone()?)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// The contents of a PEM file, or why it couldn't be read.
+type Pem = Result<Vec<u8>, String>;
+
+fn read(path: &Path) -> Pem {
+    std::fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn bearer(secret: &str) -> Result<MetadataValue<Ascii>, String> {
+    format!("Bearer {}", secret)
+        .parse()
+        .map_err(|_| "Tokens must be printable ASCII".to_string())
+}
+
+/// Attaches a shared secret to every request of a tester.
+#[derive(Clone, Debug, Default)]
+pub struct SendToken(Option<MetadataValue<Ascii>>);
+
+impl SendToken {
+    /// Sends `secret`, or nothing if it is `None`.
+    pub fn new(secret: Option<&str>) -> Result<SendToken, String> {
+        secret.map(bearer).transpose().map(SendToken)
+    }
+}
+
+impl Interceptor for SendToken {
+    fn call(
+        &mut self,
+        mut request: Request<()>,
+    ) -> Result<Request<()>, Status> {
+        if let Some(token) = &self.0 {
+            request.metadata_mut().insert(TOKEN_KEY, token.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Rejects the requests to a runner that lack its shared secret.
+#[derive(Clone, Debug, Default)]
+pub struct RequireToken(Option<MetadataValue<Ascii>>);
+
+impl RequireToken {
+    /// Requires `secret`, or accepts every request if it is `None`.
+    pub fn new(secret: Option<&str>) -> Result<RequireToken, String> {
+        secret.map(bearer).transpose().map(RequireToken)
+    }
+}
+
+impl Interceptor for RequireToken {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(expected) = &self.0 else {
+            return Ok(request);
+        };
+        match request.metadata().get(TOKEN_KEY) {
+            Some(token) if same(token.as_bytes(), expected.as_bytes()) => {
+                Ok(request)
+            }
+            Some(_) => Err(Status::unauthenticated("Invalid token")),
+            None => Err(Status::unauthenticated("Missing token")),
+        }
+    }
+}
+
+/// Compares secrets in a time that doesn't depend on where they differ.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |d, (x, y)| d | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use tonic::service::Interceptor;
+    use tonic::{Code, Request};
+
+    use super::{RequireToken, SendToken};
+
+    #[test]
+    fn tokens() {
+        let mut send = SendToken::new(Some("s3cret")).unwrap();
+        let mut require = RequireToken::new(Some("s3cret")).unwrap();
+        let request = send.call(Request::new(())).unwrap();
+        assert!(require.call(request).is_ok());
+
+        let missing = require.call(Request::new(())).unwrap_err();
+        assert_eq!(missing.code(), Code::Unauthenticated);
+        let mut wrong = SendToken::new(Some("guess")).unwrap();
+        let request = wrong.call(Request::new(())).unwrap();
+        assert_eq!(
+            require.call(request).unwrap_err().code(),
+            Code::Unauthenticated
+        );
+
+        let mut open = RequireToken::new(None).unwrap();
+        assert!(open.call(Request::new(())).is_ok());
+        assert!(SendToken::new(Some("line\nbreak")).is_err());
+    }
+}
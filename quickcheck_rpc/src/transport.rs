@@ -0,0 +1,146 @@
+//! Connections from testers to runners, over every transport an endpoint
+//! can name.
+
+use std::io;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UnixStream;
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+use crate::in_process;
+use crate::security::Tls;
+
+/// Connects to the runner at `endpoint`, which is one of:
+///
+/// * a URL such as `http://127.0.0.1:50051`, for a runner listening on TCP,
+/// * `unix:PATH`, for a runner listening on a Unix domain socket,
+/// * `stdio:COMMAND`, for a runner spawned with `COMMAND` (split on
+///   whitespace) that serves over its stdin and stdout,
+/// * `inproc:NAME`, for a runner serving on `inproc:NAME` in this process.
+///
+/// `tls` is used for `https` endpoints. A runner process is killed once the
+/// channel, and every clone of it, is dropped.
+pub async fn channel(
+    endpoint: &str,
+    tls: Option<&Tls>,
+) -> Result<Channel, String> {
+    if let Some(path) = endpoint.strip_prefix("unix:") {
+        unix_channel(path.to_string()).await
+    } else if let Some(name) = endpoint.strip_prefix("inproc:") {
+        in_process::channel(name)
+            .ok_or_else(|| format!("No in-process runner named `{}`", name))
+    } else if let Some(command) = endpoint.strip_prefix("stdio:") {
+        stdio_channel(command).await
+    } else {
+        let mut uri = Endpoint::from_shared(endpoint.to_string())
+            .map_err(|e| e.to_string())?;
+        if let (Some(tls), true) = (tls, endpoint.starts_with("https:")) {
+            uri = uri
+                .tls_config(tls.client_config()?)
+                .map_err(|e| e.to_string())?;
+        }
+        uri.connect().await.map_err(|e| format!("{}: {}", e, source(&e)))
+    }
+}
+
+/// The URI of connections made through a connector, which is never resolved.
+const LOCAL: &str = "http://localhost";
+
+async fn unix_channel(path: String) -> Result<Channel, String> {
+    Endpoint::from_static(LOCAL)
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let path = path.clone();
+            async move { UnixStream::connect(path).await.map(TokioIo::new) }
+        }))
+        .await
+        .map_err(|e| format!("{}: {}", e, source(&e)))
+}
+
+/// Spawns a runner and connects to it over its stdin and stdout.
+async fn stdio_channel(command: &str) -> Result<Channel, String> {
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or("No runner command given")?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn runner `{}`: {}", command, e))?;
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    // A process has only one pair of pipes, so it can only be connected to
+    // once.
+    let pipes = Mutex::new(Some(ChildPipes { _child: child, stdin, stdout }));
+    Endpoint::from_static(LOCAL)
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let pipes = pipes.lock().unwrap().take();
+            async move {
+                pipes.map(TokioIo::new).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotConnected,
+                        "the runner process has already disconnected",
+                    )
+                })
+            }
+        }))
+        .await
+        .map_err(|e| format!("{}: {}", e, source(&e)))
+}
+
+/// Describes the cause of a connection error, which tonic leaves out of its
+/// message.
+fn source(e: &tonic::transport::Error) -> String {
+    std::error::Error::source(e)
+        .map(|source| source.to_string())
+        .unwrap_or_default()
+}
+
+/// The stdin and stdout of a runner process, which lives as long as they are
+/// open.
+struct ChildPipes {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl AsyncRead for ChildPipes {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ChildPipes {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stdin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdin).poll_shutdown(cx)
+    }
+}
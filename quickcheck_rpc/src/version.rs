@@ -0,0 +1,167 @@
+//! Versions of the protocol, and their negotiation with `Handshake`.
+
+use crate::{HandshakeRequest, HandshakeResponse};
+
+/// The newest protocol version this crate speaks.
+///
+/// Version 1 is the protocol before `Handshake`, which runners that answer
+/// it with `UNIMPLEMENTED` speak, and has only `Execute`. Version 2 added
+/// `Handshake`, `ExecuteBatch`, `Run`, `Generate`, `Shrink`, `Describe`,
+/// sessions and the `version` of `ExecuteRequest` and `ExecuteResponse`.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The first protocol version with RPCs other than `Execute`.
+pub const EXTENSIONS_VERSION: u32 = 2;
+
+/// The oldest protocol version this crate still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// The highest version in both ranges, if they overlap.
+pub fn negotiate(ours: (u32, u32), theirs: (u32, u32)) -> Option<u32> {
+    let version = ours.1.min(theirs.1);
+    (version >= ours.0.max(theirs.0)).then_some(version)
+}
+
+/// The request a tester speaking this crate's versions sends.
+pub fn request() -> HandshakeRequest {
+    HandshakeRequest {
+        min_version: MIN_PROTOCOL_VERSION,
+        max_version: PROTOCOL_VERSION,
+    }
+}
+
+/// The response of a runner speaking this crate's versions to `request`.
+pub fn respond(
+    request: &HandshakeRequest,
+    implementation: &str,
+) -> HandshakeResponse {
+    let ours = (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+    let theirs = (request.min_version, request.max_version);
+    HandshakeResponse {
+        version: negotiate(ours, theirs).unwrap_or(0),
+        min_version: MIN_PROTOCOL_VERSION,
+        max_version: PROTOCOL_VERSION,
+        implementation: implementation.to_string(),
+    }
+}
+
+/// Checks the response of a runner to `request`, returning the version to
+/// speak with it.
+pub fn accept(response: &HandshakeResponse) -> Result<u32, String> {
+    let ours = (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+    let theirs = (response.min_version, response.max_version);
+    match negotiate(ours, theirs) {
+        Some(version) if version == response.version => Ok(version),
+        Some(version) => Err(format!(
+            "Runner `{}` chose protocol version {} instead of {}",
+            response.implementation, response.version, version
+        )),
+        None => Err(format!(
+            "Runner `{}` speaks protocol versions {} to {}, but this tester \
+             speaks {} to {}",
+            response.implementation,
+            response.min_version,
+            response.max_version,
+            MIN_PROTOCOL_VERSION,
+            PROTOCOL_VERSION
+        )),
+    }
+}
+
+/// The version to speak with a runner that answers `Handshake` with
+/// `UNIMPLEMENTED`.
+pub fn accept_unimplemented() -> Result<u32, String> {
+    let ours = (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+    negotiate(ours, (1, 1)).ok_or_else(|| {
+        format!(
+            "Runner does not implement Handshake, so speaks protocol version \
+             1, but this tester speaks {} to {}",
+            MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        )
+    })
+}
+
+/// Checks the version an `Execute` request was sent with, returning the
+/// version to answer it with.
+///
+/// Requests without a version come from testers older than the field, which
+/// speak version 1. Runners reject the others they don't speak, so a call
+/// in the wrong version fails even without a `Handshake`.
+pub fn check_request(version: u32) -> Result<u32, String> {
+    match version {
+        0 => Ok(1),
+        MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION => Ok(version),
+        _ => Err(format!(
+            "Request uses protocol version {}, but this runner speaks {} to \
+             {}",
+            version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        )),
+    }
+}
+
+/// Checks the version a runner answered an `Execute` request sent with
+/// version `sent` in.
+///
+/// Responses without a version come from runners older than the field.
+pub fn check_response(sent: u32, received: u32) -> Result<(), String> {
+    if received == 0 || received == sent {
+        Ok(())
+    } else {
+        Err(format!(
+            "Runner answered with protocol version {} a request sent with \
+             version {}",
+            received, sent
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        PROTOCOL_VERSION, accept, accept_unimplemented, check_request,
+        check_response, negotiate, request, respond,
+    };
+    use crate::HandshakeRequest;
+
+    #[test]
+    fn negotiation() {
+        assert_eq!(negotiate((1, 2), (1, 2)), Some(2));
+        assert_eq!(negotiate((1, 3), (2, 5)), Some(3));
+        assert_eq!(negotiate((2, 4), (1, 2)), Some(2));
+        assert_eq!(negotiate((1, 2), (3, 4)), None);
+        assert_eq!(negotiate((3, 4), (1, 2)), None);
+
+        let response = respond(&request(), "test");
+        assert_eq!(accept(&response), Ok(PROTOCOL_VERSION));
+
+        let future = HandshakeRequest { min_version: 7, max_version: 9 };
+        let response = respond(&future, "test");
+        assert_eq!(response.version, 0);
+        let mut newer = response.clone();
+        newer.min_version = 7;
+        newer.max_version = 9;
+        assert_eq!(
+            accept(&newer),
+            Err("Runner `test` speaks protocol versions 7 to 9, but this \
+                 tester speaks 1 to 2"
+                .to_string())
+        );
+        assert!(accept(&response).is_err());
+        assert_eq!(accept_unimplemented(), Ok(1));
+    }
+
+    #[test]
+    fn execute_versions() {
+        assert_eq!(check_request(0), Ok(1));
+        assert_eq!(check_request(PROTOCOL_VERSION), Ok(PROTOCOL_VERSION));
+        assert_eq!(
+            check_request(9),
+            Err("Request uses protocol version 9, but this runner speaks 1 \
+                 to 2"
+                .to_string())
+        );
+        assert_eq!(check_response(2, 0), Ok(()));
+        assert_eq!(check_response(2, 2), Ok(()));
+        assert!(check_response(2, 1).is_err());
+    }
+}
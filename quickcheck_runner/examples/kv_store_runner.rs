@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use quickcheck_runner::{StatefulTestFunction, StatefulTestRunner};
+
+#[derive(Deserialize, Serialize, Debug)]
+enum KvCommand {
+    Put(String, i64),
+    Get(String),
+    Delete(String),
+}
+
+/// 每个会话拥有一个独立的键值存储
+struct KvStore;
+
+impl StatefulTestFunction for KvStore {
+    type State = Mutex<HashMap<String, i64>>;
+    type Command = KvCommand;
+    type Return = Option<i64>;
+    const TEST_ID: &'static str = "kv_store";
+
+    fn begin(&self) -> Result<Self::State, String> {
+        Ok(Mutex::new(HashMap::new()))
+    }
+
+    fn execute(&self, state: &Self::State, command: KvCommand) -> Result<Option<i64>, String> {
+        let mut map = state.lock().map_err(|e| e.to_string())?;
+        Ok(match command {
+            KvCommand::Put(key, value) => map.insert(key, value),
+            KvCommand::Get(key) => map.get(&key).copied(),
+            KvCommand::Delete(key) => map.remove(&key),
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    StatefulTestRunner::new(KvStore).run("[::1]:50051").await
+}
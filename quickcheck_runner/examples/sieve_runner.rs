@@ -29,7 +29,7 @@ fn sieve_test(args: SieveArgs) -> Result<bool, String> {
     }
 
     fn is_prime(n: usize) -> bool {
-        n != 0 && n != 1 && (2..).take_while(|i| i * i <= n).all(|i| n % i != 0)
+        n != 0 && n != 1 && (2..).take_while(|i| i * i <= n).all(|i| !n.is_multiple_of(i))
     }
 
     // 检查筛法结果是否都是素数
@@ -1,129 +1,1178 @@
-use std::panic::{self, AssertUnwindSafe};
-use serde::Deserialize;
-use tonic::{transport::Server, Request, Response, Status};
+use hyper_util::rt::TokioIo;
 use quickcheck_rpc::{
-    execute_response, test_runner_server::{TestRunner, TestRunnerServer}, 
-    ExecuteRequest, ExecuteResponse
+    execute_response, in_process,
+    run_response::Event,
+    test_runner_server::{TestRunner, TestRunnerServer},
+    version, BeginSessionRequest, BeginSessionResponse, CaseResult,
+    CoverRequirement, DescribeRequest, DescribeResponse, EndSessionRequest,
+    EndSessionResponse, ExecuteBatchRequest, ExecuteBatchResponse,
+    ExecuteRequest, ExecuteResponse, GenerateRequest, GenerateResponse,
+    HandshakeRequest, HandshakeResponse, RunLog, RunRequest, RunResponse,
+    ShrinkRequest, ShrinkResponse, TableEntry,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::DuplexStream;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::{
+    wrappers::{ReceiverStream, UnboundedReceiverStream, UnixListenerStream},
+    Stream, StreamExt,
 };
+use tonic::{
+    transport::{
+        server::{Connected, TcpIncoming},
+        Endpoint, Server, Uri,
+    },
+    Request, Response, Status, Streaming,
+};
+use tower::service_fn;
+
+pub use quickcheck_rpc::codec::Codec;
+pub use quickcheck_rpc::schema::{schema_of, Schema};
+use quickcheck_rpc::security::RequireToken;
+pub use quickcheck_rpc::security::Tls;
+
+/// The stream of results and logs a runner sends back over `Run`
+pub type RunStream =
+    Pin<Box<dyn Stream<Item = Result<RunResponse, Status>> + Send>>;
 
 /// A trait for test functions that can be run by the runner
 pub trait TestFunction: Send + Sync + 'static {
     /// The argument type for this test function
     type Args: for<'de> Deserialize<'de> + Send + Sync + 'static;
-    
+
     /// The return type of the test function
     type Return: serde::Serialize + Send + Sync + 'static;
-    
+
     /// The unique name for this test function
     const TEST_ID: &'static str;
-    
+
     /// Execute the test function with the given arguments
     fn execute(&self, args: Self::Args) -> Result<Self::Return, String>;
+
+    /// Whether the property applies to the given arguments; cases it doesn't
+    /// apply to are discarded without being executed, and reported to the
+    /// tester as invalid input
+    fn precondition(&self, _args: &Self::Args) -> bool {
+        true
+    }
+
+    /// Labels classifying a test case, reported to the tester for its
+    /// coverage statistics
+    fn labels(&self, _args: &Self::Args) -> Vec<String> {
+        vec![]
+    }
+
+    /// Values a test case adds to the tester's statistics tables, as
+    /// `(table, value)` pairs
+    fn tables(&self, _args: &Self::Args) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Coverage requirements a test case declares, as `(label, percentage)`
+    /// pairs; the tester fails the run if fewer than `percentage` percent of
+    /// passed cases carry `label`
+    fn coverage(&self, _args: &Self::Args) -> Vec<(String, f64)> {
+        vec![]
+    }
+
+    /// Called once when a session begins, before any of its cases
+    ///
+    /// This is the place for expensive setup, such as loading fixtures or
+    /// opening a database connection, that should happen once per run
+    /// rather than once per case. A case executed outside of any session
+    /// gets its own session setup and teardown.
+    fn setup_session(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called once when a session ends
+    fn teardown_session(&self) {}
+
+    /// Called before every case
+    fn setup_case(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after every case, whether it passed or not
+    fn teardown_case(&self) {}
+
+    /// The schema of the return type, sent to the tester with `Describe` so
+    /// that it can check the type it expects, usually
+    /// `Some(schema_of::<Self::Return>())`
+    ///
+    /// Return types only need `Serialize`, so this cannot be derived for them.
+    fn return_schema(&self) -> Option<Schema> {
+        None
+    }
+}
+
+/// Generates and shrinks the arguments of a test function in the runner, for
+/// testers that leave generation to the runner's language
+pub trait Generator: Send + Sync + 'static {
+    /// The argument type, the same as the test function's
+    type Args: Serialize + for<'de> Deserialize<'de>;
+
+    /// Generates arguments from a seed and a size
+    ///
+    /// The same seed and size must always give the same arguments, so that
+    /// the tester can replay a failure.
+    fn generate(&self, seed: u64, size: usize) -> Self::Args;
+
+    /// Returns smaller versions of failing arguments, most promising first
+    fn shrink(&self, _args: &Self::Args) -> Vec<Self::Args> {
+        vec![]
+    }
+}
+
+/// A `Generator` working on encoded arguments
+trait EncodedGenerator: Send + Sync {
+    fn generate(&self, seed: u64, size: usize) -> Result<Vec<u8>, Status>;
+    fn shrink(&self, test_data: &[u8]) -> Result<Vec<Vec<u8>>, Status>;
+}
+
+impl<G: Generator> EncodedGenerator for G {
+    fn generate(&self, seed: u64, size: usize) -> Result<Vec<u8>, Status> {
+        let args = catch(|| Ok(Generator::generate(self, seed, size)))
+            .map_err(|e| {
+                Status::internal(format!(
+                    "Failed to generate arguments: {}",
+                    e
+                ))
+            })?;
+        encode_args(&args)
+    }
+
+    fn shrink(&self, test_data: &[u8]) -> Result<Vec<Vec<u8>>, Status> {
+        let args: G::Args = rmp_serde::from_slice(test_data).map_err(|e| {
+            Status::invalid_argument(format!(
+                "Failed to deserialize arguments: {}",
+                e
+            ))
+        })?;
+        let candidates = catch(|| Ok(Generator::shrink(self, &args)))
+            .map_err(|e| {
+                Status::internal(format!("Failed to shrink arguments: {}", e))
+            })?;
+        candidates.iter().map(encode_args).collect()
+    }
+}
+
+fn encode_args<A: Serialize>(args: &A) -> Result<Vec<u8>, Status> {
+    rmp_serde::to_vec_named(args).map_err(|e| {
+        Status::internal(format!("Failed to serialize arguments: {}", e))
+    })
 }
 
 /// A runner that executes a single test function
 pub struct SingleTestRunner<F: TestFunction> {
-    test_function: F,
+    test_function: Arc<F>,
+    generator: Option<Arc<dyn EncodedGenerator>>,
+    sessions: Arc<Mutex<HashSet<String>>>,
+    next_session: Arc<AtomicU64>,
+    security: Security,
+}
+
+impl<F: TestFunction> Clone for SingleTestRunner<F> {
+    fn clone(&self) -> Self {
+        Self {
+            test_function: self.test_function.clone(),
+            generator: self.generator.clone(),
+            sessions: self.sessions.clone(),
+            next_session: self.next_session.clone(),
+            security: self.security.clone(),
+        }
+    }
 }
 
 impl<F: TestFunction> SingleTestRunner<F> {
     pub fn new(test_function: F) -> Self {
-        Self { test_function }
+        Self {
+            test_function: Arc::new(test_function),
+            generator: None,
+            sessions: Arc::new(Mutex::new(HashSet::new())),
+            next_session: Arc::new(AtomicU64::new(0)),
+            security: Security::default(),
+        }
     }
-    
+
+    /// Generate and shrink arguments in this runner when the tester asks for
+    /// it with `Generate` and `Shrink`
+    pub fn generator<G: Generator<Args = F::Args>>(
+        mut self,
+        generator: G,
+    ) -> Self {
+        self.generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// Serve over TLS, requiring client certificates if `tls` has a CA
+    /// certificate
+    pub fn tls(mut self, tls: Tls) -> Self {
+        self.security.tls = Some(tls);
+        self
+    }
+
+    /// Reject requests that don't carry `secret`, as set with
+    /// `QuickCheck::token`
+    pub fn token<S: Into<String>>(mut self, secret: S) -> Self {
+        self.security.token = Some(secret.into());
+        self
+    }
+
+    fn encoded_generator(
+        &self,
+        test_id: &str,
+    ) -> Result<&dyn EncodedGenerator, Status> {
+        check_test_id(test_id, F::TEST_ID)?;
+        self.generator.as_deref().ok_or_else(|| {
+            Status::unimplemented(format!(
+                "Test '{}' does not generate its arguments",
+                F::TEST_ID
+            ))
+        })
+    }
+
     /// Start the gRPC server for this test function
-    pub async fn run(self, address: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let addr = address.parse()?;
-        
-        println!("Starting gRPC Runner for '{}' on {}", F::TEST_ID, addr);
+    ///
+    /// The address is a TCP socket address, `unix:PATH` for a Unix domain
+    /// socket or `stdio` to serve the tester that spawned this process over
+    /// its stdin and stdout.
+    pub async fn run(
+        self,
+        address: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        announce(
+            format!(
+                "Starting gRPC Runner for '{}' on {}",
+                F::TEST_ID,
+                address
+            ),
+            address,
+        );
+        let security = self.security.clone();
+        serve(self, &security, Listener::bind(address).await?).await
+    }
 
-        Server::builder()
-            .add_service(TestRunnerServer::new(self))
-            .serve(addr)
-            .await?;
+    /// Start the gRPC server for this test function in the background,
+    /// returning once it accepts connections
+    ///
+    /// Returns the endpoint testers reach the runner at, which for a TCP
+    /// address with port 0 has the port the runner was given. Serving over
+    /// `stdio` cannot be done in the background.
+    pub async fn spawn(
+        self,
+        address: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let security = self.security.clone();
+        spawn(self, security, address).await
+    }
 
-        Ok(())
+    /// Executes one case, as requested by `Execute` or `ExecuteBatch`
+    fn execute_case(
+        &self,
+        req: ExecuteRequest,
+    ) -> Result<ExecuteResponse, Status> {
+        let version = request_version(&req)?;
+        // Verify this is the correct test
+        check_test_id(&req.test_id, F::TEST_ID)?;
+        let in_session = !req.session_id.is_empty();
+        if in_session
+            && !self.sessions.lock().unwrap().contains(&req.session_id)
+        {
+            return Err(Status::not_found(format!(
+                "Session '{}' not found",
+                req.session_id
+            )));
+        }
+
+        // Deserialize the arguments
+        let codec = request_codec(&req)?;
+        let args: F::Args = codec.decode(&req.test_data).map_err(|e| {
+            Status::invalid_argument(format!(
+                "Failed to deserialize arguments: {}",
+                e
+            ))
+        })?;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| {
+            self.test_function.precondition(&args)
+        })) {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(ExecuteResponse {
+                    status: execute_response::TestStatus::InvalidInput.into(),
+                    version,
+                    ..ExecuteResponse::default()
+                })
+            }
+            Err(panic_payload) => {
+                let response =
+                    to_response::<F::Return>(codec, Err(panic_payload))?;
+                return Ok(ExecuteResponse { version, ..response });
+            }
+        }
+
+        // Classify the case before the arguments are moved into the test
+        let labels = self.test_function.labels(&args);
+        let tables = self
+            .test_function
+            .tables(&args)
+            .into_iter()
+            .map(|(table, value)| TableEntry { table, value })
+            .collect();
+        let coverage = self
+            .test_function
+            .coverage(&args)
+            .into_iter()
+            .map(|(label, percentage)| CoverRequirement { label, percentage })
+            .collect();
+
+        // A case outside of any session is its own session
+        if !in_session {
+            catch(|| self.test_function.setup_session()).map_err(|e| {
+                Status::internal(format!("Session setup failed: {}", e))
+            })?;
+        }
+        let result = match catch(|| self.test_function.setup_case()) {
+            Ok(()) => {
+                // Execute the test function with panic catching
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.test_function.execute(args)
+                }));
+                teardown(|| self.test_function.teardown_case());
+                Ok(result)
+            }
+            Err(e) => {
+                Err(Status::internal(format!("Case setup failed: {}", e)))
+            }
+        };
+        if !in_session {
+            teardown(|| self.test_function.teardown_session());
+        }
+
+        Ok(ExecuteResponse {
+            labels,
+            tables,
+            coverage,
+            version,
+            ..to_response(codec, result?)?
+        })
     }
 }
 
 #[tonic::async_trait]
 impl<F: TestFunction> TestRunner for SingleTestRunner<F> {
+    async fn handshake(
+        &self,
+        request: Request<HandshakeRequest>,
+    ) -> Result<Response<HandshakeResponse>, Status> {
+        Ok(Response::new(version::respond(request.get_ref(), IMPLEMENTATION)))
+    }
+
     async fn execute(
         &self,
         request: Request<ExecuteRequest>,
     ) -> Result<Response<ExecuteResponse>, Status> {
+        Ok(Response::new(self.execute_case(request.into_inner())?))
+    }
+
+    async fn execute_batch(
+        &self,
+        request: Request<ExecuteBatchRequest>,
+    ) -> Result<Response<ExecuteBatchResponse>, Status> {
+        let responses = request
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(|req| self.execute_case(req))
+            .collect::<Result<_, _>>()?;
+        Ok(Response::new(ExecuteBatchResponse { responses }))
+    }
+
+    type RunStream = RunStream;
+
+    async fn run(
+        &self,
+        request: Request<Streaming<RunRequest>>,
+    ) -> Result<Response<RunStream>, Status> {
+        let runner = self.clone();
+        Ok(Response::new(stream_cases(request.into_inner(), move |req| {
+            runner.execute_case(req)
+        })))
+    }
+
+    async fn generate(
+        &self,
+        request: Request<GenerateRequest>,
+    ) -> Result<Response<GenerateResponse>, Status> {
         let req = request.into_inner();
-        
-        // Verify this is the correct test
-        if req.test_id != F::TEST_ID {
+        let test_data = self
+            .encoded_generator(&req.test_id)?
+            .generate(req.seed, req.size as usize)?;
+        Ok(Response::new(GenerateResponse { test_data }))
+    }
+
+    async fn shrink(
+        &self,
+        request: Request<ShrinkRequest>,
+    ) -> Result<Response<ShrinkResponse>, Status> {
+        let req = request.into_inner();
+        let candidates =
+            self.encoded_generator(&req.test_id)?.shrink(&req.test_data)?;
+        Ok(Response::new(ShrinkResponse { candidates }))
+    }
+
+    async fn describe(
+        &self,
+        request: Request<DescribeRequest>,
+    ) -> Result<Response<DescribeResponse>, Status> {
+        check_test_id(&request.into_inner().test_id, F::TEST_ID)?;
+        let return_schema = self.test_function.return_schema();
+        describe(schema_of::<F::Args>(), return_schema)
+    }
+
+    async fn begin_session(
+        &self,
+        request: Request<BeginSessionRequest>,
+    ) -> Result<Response<BeginSessionResponse>, Status> {
+        let req = request.into_inner();
+        check_test_id(&req.test_id, F::TEST_ID)?;
+
+        catch(|| self.test_function.setup_session()).map_err(|e| {
+            Status::internal(format!("Failed to begin session: {}", e))
+        })?;
+
+        let n = self.next_session.fetch_add(1, Ordering::Relaxed);
+        let session_id = format!("{}-{}", F::TEST_ID, n);
+        self.sessions.lock().unwrap().insert(session_id.clone());
+
+        Ok(Response::new(BeginSessionResponse { session_id }))
+    }
+
+    async fn end_session(
+        &self,
+        request: Request<EndSessionRequest>,
+    ) -> Result<Response<EndSessionResponse>, Status> {
+        let req = request.into_inner();
+        if !self.sessions.lock().unwrap().remove(&req.session_id) {
             return Err(Status::not_found(format!(
-                "Test '{}' not found. This runner only supports '{}'", 
-                req.test_id, F::TEST_ID
+                "Session '{}' not found",
+                req.session_id
             )));
         }
+        teardown(|| self.test_function.teardown_session());
+        Ok(Response::new(EndSessionResponse {}))
+    }
+}
+
+/// A trait for stateful test functions, whose state is kept between the
+/// calls of one session
+///
+/// Each command of a state machine test is sent as one `Execute` call within
+/// a session. The state is shared, not locked, between concurrent calls of
+/// the same session, so it can wrap a system that is itself concurrent.
+pub trait StatefulTestFunction: Send + Sync + 'static {
+    /// The state of one session, such as the system under test
+    type State: Send + Sync + 'static;
+
+    /// The command type sent in each `Execute` call
+    type Command: for<'de> Deserialize<'de> + Send + Sync + 'static;
+
+    /// The return type of a command
+    type Return: Serialize + Send + Sync + 'static;
+
+    /// The unique name for this test function
+    const TEST_ID: &'static str;
+
+    /// Creates the state of a new session
+    fn begin(&self) -> Result<Self::State, String>;
+
+    /// Executes a command against the state of a session
+    fn execute(
+        &self,
+        state: &Self::State,
+        command: Self::Command,
+    ) -> Result<Self::Return, String>;
+
+    /// Tears down the state of a session
+    fn end(&self, _state: &Self::State) {}
+
+    /// The schema of the return type of commands, as for
+    /// `TestFunction::return_schema`
+    fn return_schema(&self) -> Option<Schema> {
+        None
+    }
+}
+
+/// A runner that executes a single stateful test function, keeping one state
+/// per session
+pub struct StatefulTestRunner<F: StatefulTestFunction> {
+    test_function: Arc<F>,
+    sessions: Arc<Mutex<HashMap<String, Arc<F::State>>>>,
+    next_session: Arc<AtomicU64>,
+    security: Security,
+}
+
+impl<F: StatefulTestFunction> Clone for StatefulTestRunner<F> {
+    fn clone(&self) -> Self {
+        Self {
+            test_function: self.test_function.clone(),
+            sessions: self.sessions.clone(),
+            next_session: self.next_session.clone(),
+            security: self.security.clone(),
+        }
+    }
+}
+
+impl<F: StatefulTestFunction> StatefulTestRunner<F> {
+    pub fn new(test_function: F) -> Self {
+        Self {
+            test_function: Arc::new(test_function),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            next_session: Arc::new(AtomicU64::new(0)),
+            security: Security::default(),
+        }
+    }
+
+    /// Serve over TLS, as with `SingleTestRunner::tls`
+    pub fn tls(mut self, tls: Tls) -> Self {
+        self.security.tls = Some(tls);
+        self
+    }
+
+    /// Reject requests that don't carry `secret`, as with
+    /// `SingleTestRunner::token`
+    pub fn token<S: Into<String>>(mut self, secret: S) -> Self {
+        self.security.token = Some(secret.into());
+        self
+    }
+
+    /// Start the gRPC server for this test function, on an address as for
+    /// `SingleTestRunner::run`
+    pub async fn run(
+        self,
+        address: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        announce(
+            format!(
+                "Starting stateful gRPC Runner for '{}' on {}",
+                F::TEST_ID,
+                address
+            ),
+            address,
+        );
+        let security = self.security.clone();
+        serve(self, &security, Listener::bind(address).await?).await
+    }
+
+    /// Start the gRPC server for this test function in the background, as
+    /// with `SingleTestRunner::spawn`
+    pub async fn spawn(
+        self,
+        address: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let security = self.security.clone();
+        spawn(self, security, address).await
+    }
+
+    fn session(&self, session_id: &str) -> Result<Arc<F::State>, Status> {
+        self.sessions.lock().unwrap().get(session_id).cloned().ok_or_else(
+            || {
+                Status::not_found(format!(
+                    "Session '{}' not found",
+                    session_id
+                ))
+            },
+        )
+    }
+
+    /// Executes one command, as requested by `Execute` or `ExecuteBatch`
+    fn execute_command(
+        &self,
+        req: ExecuteRequest,
+    ) -> Result<ExecuteResponse, Status> {
+        let version = request_version(&req)?;
+        check_test_id(&req.test_id, F::TEST_ID)?;
+        if req.session_id.is_empty() {
+            return Err(Status::invalid_argument(format!(
+                "Test '{}' is stateful and must be executed within a session",
+                F::TEST_ID
+            )));
+        }
+        let state = self.session(&req.session_id)?;
+
+        let codec = request_codec(&req)?;
+        let command: F::Command =
+            codec.decode(&req.test_data).map_err(|e| {
+                Status::invalid_argument(format!(
+                    "Failed to deserialize command: {}",
+                    e
+                ))
+            })?;
 
-        // Deserialize the arguments
-        let args: F::Args = rmp_serde::from_slice(&req.test_data)
-            .map_err(|e| Status::invalid_argument(format!("Failed to deserialize arguments: {}", e)))?;
-        
-        // Execute the test function with panic catching
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
-            self.test_function.execute(args)
+            self.test_function.execute(&state, command)
         }));
 
-        // Convert the result to the gRPC ExecuteResponse
-        let (status, failure_detail, return_value) = match result {
-            Ok(Ok(return_value)) => {
-                // Success case - convert return value to MessagePack
-                let return_value = rmp_serde::to_vec_named(&return_value)
-                    .map_err(|e| Status::internal(format!("Failed to serialize return value: {}", e)))?;
-                (execute_response::TestStatus::Passed, None, Some(return_value))
+        Ok(ExecuteResponse { version, ..to_response(codec, result)? })
+    }
+}
+
+#[tonic::async_trait]
+impl<F: StatefulTestFunction> TestRunner for StatefulTestRunner<F> {
+    async fn handshake(
+        &self,
+        request: Request<HandshakeRequest>,
+    ) -> Result<Response<HandshakeResponse>, Status> {
+        Ok(Response::new(version::respond(request.get_ref(), IMPLEMENTATION)))
+    }
+
+    async fn execute(
+        &self,
+        request: Request<ExecuteRequest>,
+    ) -> Result<Response<ExecuteResponse>, Status> {
+        Ok(Response::new(self.execute_command(request.into_inner())?))
+    }
+
+    /// Executes the commands in order, as if each were its own `Execute`
+    async fn execute_batch(
+        &self,
+        request: Request<ExecuteBatchRequest>,
+    ) -> Result<Response<ExecuteBatchResponse>, Status> {
+        let responses = request
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(|req| self.execute_command(req))
+            .collect::<Result<_, _>>()?;
+        Ok(Response::new(ExecuteBatchResponse { responses }))
+    }
+
+    type RunStream = RunStream;
+
+    /// Executes the commands in the order they arrive, as if each were its
+    /// own `Execute`
+    async fn run(
+        &self,
+        request: Request<Streaming<RunRequest>>,
+    ) -> Result<Response<RunStream>, Status> {
+        let runner = self.clone();
+        Ok(Response::new(stream_cases(request.into_inner(), move |req| {
+            runner.execute_command(req)
+        })))
+    }
+
+    /// Commands are generated by the tester's state machine model
+    async fn generate(
+        &self,
+        _request: Request<GenerateRequest>,
+    ) -> Result<Response<GenerateResponse>, Status> {
+        Err(Status::unimplemented("Stateful runners do not generate commands"))
+    }
+
+    async fn shrink(
+        &self,
+        _request: Request<ShrinkRequest>,
+    ) -> Result<Response<ShrinkResponse>, Status> {
+        Err(Status::unimplemented("Stateful runners do not shrink commands"))
+    }
+
+    /// Describes the command type, since commands are sent one by one
+    async fn describe(
+        &self,
+        request: Request<DescribeRequest>,
+    ) -> Result<Response<DescribeResponse>, Status> {
+        check_test_id(&request.into_inner().test_id, F::TEST_ID)?;
+        let return_schema = self.test_function.return_schema();
+        describe(schema_of::<F::Command>(), return_schema)
+    }
+
+    async fn begin_session(
+        &self,
+        request: Request<BeginSessionRequest>,
+    ) -> Result<Response<BeginSessionResponse>, Status> {
+        let req = request.into_inner();
+        check_test_id(&req.test_id, F::TEST_ID)?;
+
+        let state = catch(|| self.test_function.begin()).map_err(|e| {
+            Status::internal(format!("Failed to begin session: {}", e))
+        })?;
+
+        let n = self.next_session.fetch_add(1, Ordering::Relaxed);
+        let session_id = format!("{}-{}", F::TEST_ID, n);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.clone(), Arc::new(state));
+
+        Ok(Response::new(BeginSessionResponse { session_id }))
+    }
+
+    async fn end_session(
+        &self,
+        request: Request<EndSessionRequest>,
+    ) -> Result<Response<EndSessionResponse>, Status> {
+        let req = request.into_inner();
+        let state = self.session(&req.session_id)?;
+        self.sessions.lock().unwrap().remove(&req.session_id);
+        teardown(|| self.test_function.end(&state));
+        Ok(Response::new(EndSessionResponse {}))
+    }
+}
+
+thread_local! {
+    /// The messages logged by the case running on this thread, if they are
+    /// being captured
+    static LOGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Logs a message from a test function
+///
+/// When the case is executed over a `Run` stream, the message is sent back
+/// to the tester along with the case. Otherwise it is printed.
+pub fn log<S: Into<String>>(message: S) {
+    let message = message.into();
+    LOGS.with(|logs| match logs.borrow_mut().as_mut() {
+        Some(logs) => logs.push(message),
+        None if SERVING_STDIO.load(Ordering::Relaxed) => {
+            eprintln!("{}", message)
+        }
+        None => println!("{}", message),
+    });
+}
+
+/// The address that makes a runner serve over its stdin and stdout
+const STDIO: &str = "stdio";
+
+/// The runner implementation reported by `Handshake`
+const IMPLEMENTATION: &str =
+    concat!("quickcheck_runner ", env!("CARGO_PKG_VERSION"));
+
+/// How a runner secures its connections
+#[derive(Clone, Default)]
+struct Security {
+    tls: Option<Tls>,
+    token: Option<String>,
+}
+
+/// The size of the pipes between in-process runners and their testers
+const IN_PROCESS_BUFFER: usize = 64 * 1024;
+
+/// Whether this process serves over its stdout, which must then carry
+/// nothing but the tester's connection
+static SERVING_STDIO: AtomicBool = AtomicBool::new(false);
+
+/// Prints a message about the runner, to stderr when stdout is taken
+fn announce(message: String, address: &str) {
+    if address == STDIO {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Where a runner accepts connections from testers
+enum Listener {
+    Stdio,
+    Unix(UnixListener, String),
+    InProcess(mpsc::UnboundedReceiver<DuplexStream>, String),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    /// Starts accepting connections on a TCP socket address, a Unix domain
+    /// socket given as `unix:PATH`, stdin and stdout given as `stdio`, or
+    /// in-memory pipes to testers in this process given as `inproc:NAME`
+    async fn bind(
+        address: &str,
+    ) -> Result<Listener, Box<dyn std::error::Error>> {
+        if address == STDIO {
+            Ok(Listener::Stdio)
+        } else if let Some(path) = address.strip_prefix("unix:") {
+            // A socket left behind by an earlier run would make binding
+            // fail, but anything else at the path is not ours to remove
+            match std::fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.file_type().is_socket() => {
+                    std::fs::remove_file(path)?
+                }
+                Ok(_) => return Err(format!(
+                    "Cannot serve on {}: the path exists and is not a socket",
+                    address
+                )
+                .into()),
+                Err(_) => {}
             }
-            Ok(Err(error_msg)) => {
-                // Normal error case - return error details
-                (execute_response::TestStatus::Failed, Some(error_msg), None)
+            Ok(Listener::Unix(UnixListener::bind(path)?, address.to_string()))
+        } else if let Some(name) = address.strip_prefix("inproc:") {
+            // Every connection is a fresh pair of pipes, whose other end is
+            // handed to the server
+            let (connections, incoming) = mpsc::unbounded_channel();
+            let channel = Endpoint::from_static("http://localhost")
+                .connect_with_connector_lazy(service_fn(move |_: Uri| {
+                    let (client, server) =
+                        tokio::io::duplex(IN_PROCESS_BUFFER);
+                    let sent = connections.send(server);
+                    async move {
+                        sent.map(|()| TokioIo::new(client)).map_err(|_| {
+                            std::io::Error::new(
+                                std::io::ErrorKind::NotConnected,
+                                "the in-process runner has stopped",
+                            )
+                        })
+                    }
+                }));
+            in_process::register(name, channel);
+            Ok(Listener::InProcess(incoming, address.to_string()))
+        } else {
+            Ok(Listener::Tcp(
+                TcpListener::bind(address.parse::<SocketAddr>()?).await?,
+            ))
+        }
+    }
+
+    /// The endpoint a tester connects to this listener with
+    fn endpoint(
+        &self,
+        security: &Security,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            Listener::Stdio => {
+                Err("a runner serving over stdio has no endpoint".into())
             }
-            Err(panic_payload) => {
-                // Panic case - convert panic to error message
-                let panic_msg = if let Some(s) = panic_payload.downcast_ref::<&str>() {
-                    s.to_string()
-                } else if let Some(s) = panic_payload.downcast_ref::<String>() {
-                    s.clone()
-                } else {
-                    "Unknown panic occurred".to_string()
-                };
-                (execute_response::TestStatus::Failed, Some(format!("Panic: {}", panic_msg)), None)
+            Listener::Unix(_, address) | Listener::InProcess(_, address) => {
+                Ok(address.clone())
             }
-        };
+            Listener::Tcp(listener) => {
+                let scheme =
+                    if security.tls.is_some() { "https" } else { "http" };
+                Ok(format!("{}://{}", scheme, listener.local_addr()?))
+            }
+        }
+    }
+}
 
-        let response = ExecuteResponse {
-            status: status.into(),
-            failure_detail,
-            return_value,
-        };
+/// Serves a runner on a listener until the listener is closed
+async fn serve<S: TestRunner>(
+    runner: S,
+    security: &Security,
+    listener: Listener,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = Server::builder();
+    if let Some(tls) = &security.tls {
+        builder = builder.tls_config(tls.server_config()?)?;
+    }
+    let token = RequireToken::new(security.token.as_deref())?;
+    let server =
+        builder.add_service(TestRunnerServer::with_interceptor(runner, token));
+    match listener {
+        Listener::Stdio => {
+            // The tester that spawned this process is the only client, and is
+            // gone once it closes our stdin.
+            SERVING_STDIO.store(true, Ordering::Relaxed);
+            let (closed, on_closed) = oneshot::channel();
+            let connection = StdioConnection {
+                stdin: tokio::io::stdin(),
+                stdout: tokio::io::stdout(),
+                closed: Some(closed),
+            };
+            let incoming =
+                tokio_stream::once(Ok::<_, std::io::Error>(connection))
+                    .chain(tokio_stream::pending());
+            server
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = on_closed.await;
+                })
+                .await?;
+        }
+        Listener::Unix(listener, _) => {
+            server
+                .serve_with_incoming(UnixListenerStream::new(listener))
+                .await?;
+        }
+        Listener::InProcess(incoming, _) => {
+            server
+                .serve_with_incoming(
+                    UnboundedReceiverStream::new(incoming)
+                        .map(Ok::<_, std::io::Error>),
+                )
+                .await?;
+        }
+        Listener::Tcp(listener) => {
+            server.serve_with_incoming(TcpIncoming::from(listener)).await?;
+        }
+    }
+    Ok(())
+}
 
-        Ok(Response::new(response))
+/// Serves a runner in the background, returning the endpoint testers reach
+/// it at once it accepts connections
+async fn spawn<S: TestRunner>(
+    runner: S,
+    security: Security,
+    address: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let listener = Listener::bind(address).await?;
+    let endpoint = listener.endpoint(&security)?;
+    let address = endpoint.clone();
+    tokio::spawn(async move {
+        if let Err(e) = serve(runner, &security, listener).await {
+            eprintln!("Runner at {} stopped: {}", address, e);
+        }
+    });
+    Ok(endpoint)
+}
+
+/// The connection to the tester over stdin and stdout
+struct StdioConnection {
+    stdin: tokio::io::Stdin,
+    stdout: tokio::io::Stdout,
+    /// Signalled when stdin is closed
+    closed: Option<oneshot::Sender<()>>,
+}
+
+impl Connected for StdioConnection {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) {}
+}
+
+impl AsyncRead for StdioConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.stdin).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            if buf.filled().len() == filled {
+                if let Some(closed) = self.closed.take() {
+                    let _ = closed.send(());
+                }
+            }
+        }
+        poll
     }
 }
 
+impl AsyncWrite for StdioConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stdout).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_shutdown(cx)
+    }
+}
+
+/// Runs `f`, returning its result and the messages it logged
+fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    LOGS.with(|logs| *logs.borrow_mut() = Some(vec![]));
+    let result = f();
+    let logs = LOGS.with(|logs| logs.borrow_mut().take()).unwrap_or_default();
+    (result, logs)
+}
+
+/// Executes the cases of a `Run` stream one after another, streaming back
+/// their results and the messages they log
+///
+/// An invalid case is answered with an error and does not end the stream.
+fn stream_cases<E>(mut cases: Streaming<RunRequest>, execute: E) -> RunStream
+where
+    E: Fn(ExecuteRequest) -> Result<ExecuteResponse, Status> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            let request = match cases.message().await {
+                Ok(Some(request)) => request,
+                Ok(None) => break,
+                Err(status) => {
+                    let _ = tx.send(Err(status)).await;
+                    break;
+                }
+            };
+            let case_id = request.case_id;
+            let (result, logs) =
+                capture_logs(|| execute(request.case.unwrap_or_default()));
+            for message in logs {
+                let event = Event::Log(RunLog { case_id, message });
+                if tx
+                    .send(Ok(RunResponse { event: Some(event) }))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            let result = match result {
+                Ok(response) => CaseResult {
+                    case_id,
+                    response: Some(response),
+                    error: None,
+                },
+                Err(status) => CaseResult {
+                    case_id,
+                    response: None,
+                    error: Some(status.to_string()),
+                },
+            };
+            let event = Event::Result(result);
+            if tx.send(Ok(RunResponse { event: Some(event) })).await.is_err() {
+                return;
+            }
+        }
+    });
+    Box::pin(ReceiverStream::new(rx))
+}
+
+/// Verifies that a request is for the test this runner executes
+fn check_test_id(test_id: &str, expected: &str) -> Result<(), Status> {
+    if test_id != expected {
+        return Err(Status::not_found(format!(
+            "Test '{}' not found. This runner only supports '{}'",
+            test_id, expected
+        )));
+    }
+    Ok(())
+}
+
+/// Encodes the schemas of a test for `Describe`
+fn describe(
+    args: Schema,
+    return_value: Option<Schema>,
+) -> Result<Response<DescribeResponse>, Status> {
+    let encode = |schema: &Schema| {
+        rmp_serde::to_vec_named(schema).map_err(|e| {
+            Status::internal(format!("Failed to serialize schema: {}", e))
+        })
+    };
+    Ok(Response::new(DescribeResponse {
+        args_schema: encode(&args)?,
+        return_schema: return_value.as_ref().map(encode).transpose()?,
+    }))
+}
+
+/// Runs a hook, turning a panic into an error
+fn catch<T>(hook: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(hook)).unwrap_or_else(
+        |panic_payload| {
+            Err(format!("Panic: {}", panic_message(panic_payload)))
+        },
+    )
+}
+
+/// Runs a teardown hook, ignoring a panic so that it cannot hide the result
+fn teardown(hook: impl FnOnce()) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(hook));
+}
+
+/// Extracts the message of a caught panic
+fn panic_message(panic_payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = panic_payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic_payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Unknown panic occurred".to_string()
+    }
+}
+
+/// The protocol version a request was sent with, which its response carries
+fn request_version(req: &ExecuteRequest) -> Result<u32, Status> {
+    version::check_request(req.version).map_err(Status::failed_precondition)
+}
+
+/// The codec of a request's arguments, which its return value is encoded with
+fn request_codec(req: &ExecuteRequest) -> Result<Codec, Status> {
+    Codec::try_from(req.encoding).map_err(Status::invalid_argument)
+}
+
+/// Converts the result of a test function to the gRPC ExecuteResponse
+fn to_response<R: Serialize>(
+    codec: Codec,
+    result: std::thread::Result<Result<R, String>>,
+) -> Result<ExecuteResponse, Status> {
+    let (status, failure_detail, return_value) = match result {
+        Ok(Ok(return_value)) => {
+            // Success case - encode the return value like the arguments
+            let return_value = codec.encode(&return_value).map_err(|e| {
+                Status::internal(format!(
+                    "Failed to serialize return value: {}",
+                    e
+                ))
+            })?;
+            (execute_response::TestStatus::Passed, None, Some(return_value))
+        }
+        Ok(Err(error_msg)) => {
+            // Normal error case - return error details
+            (execute_response::TestStatus::Failed, Some(error_msg), None)
+        }
+        Err(panic_payload) => {
+            // Panic case - convert panic to error message
+            let panic_msg = panic_message(panic_payload);
+            (
+                execute_response::TestStatus::Failed,
+                Some(format!("Panic: {}", panic_msg)),
+                None,
+            )
+        }
+    };
+
+    Ok(ExecuteResponse {
+        status: status.into(),
+        failure_detail,
+        return_value,
+        ..ExecuteResponse::default()
+    })
+}
+
 /// Convenience macro for creating a binary that runs a test function
 #[macro_export]
 macro_rules! quickcheck_runner_main {
     ($test_fn:expr, $args_ty:ty, $return_ty:ty, $test_id:expr) => {
-        use quickcheck_runner::{TestFunction, SingleTestRunner};
-        
+        use quickcheck_runner::{SingleTestRunner, TestFunction};
+
         struct TestFnWrapper;
-        
+
         impl TestFunction for TestFnWrapper {
             type Args = $args_ty;
             type Return = $return_ty;
             const TEST_ID: &'static str = $test_id;
-            
-            fn execute(&self, args: Self::Args) -> Result<Self::Return, String> {
+
+            fn execute(
+                &self,
+                args: Self::Args,
+            ) -> Result<Self::Return, String> {
                 $test_fn(args)
             }
         }
-        
+
         #[tokio::main]
         async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let runner = SingleTestRunner::new(TestFnWrapper);
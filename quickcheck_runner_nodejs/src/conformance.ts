@@ -0,0 +1,50 @@
+import { startServer, TestFunction } from './server.js';
+
+// 一致性测试所需的测试函数，名称由 quickcheck_conformance 约定
+// 启动后运行：
+//   cargo run -p quickcheck_conformance -- http://[::1]:50061 \
+//     --echo http://[::1]:50061 --panic http://[::1]:50062 --discard http://[::1]:50063 \
+//     --precondition-panic http://[::1]:50064
+
+// 原样返回参数，用于检查返回值的编码往返
+const echo: TestFunction = {
+  testId: 'conformance_echo',
+  execute(args: any) {
+    return args;
+  }
+};
+
+// 总是抛出异常，用于检查失败的报告
+const panic: TestFunction = {
+  testId: 'conformance_panic',
+  execute() {
+    throw new Error('conformance panic');
+  }
+};
+
+// 前置条件总是不满足，用于检查丢弃的报告
+const discard: TestFunction = {
+  testId: 'conformance_discard',
+  precondition() {
+    return false;
+  },
+  execute() {
+    return null;
+  }
+};
+
+// 前置条件总是抛出异常，用于检查前置条件中的异常是否报告为失败
+const preconditionPanic: TestFunction = {
+  testId: 'conformance_precondition_panic',
+  precondition(): boolean {
+    throw new Error('conformance precondition panic');
+  },
+  execute() {
+    return null;
+  }
+};
+
+await startServer(echo, '[::1]:50061');
+await startServer(panic, '[::1]:50062');
+await startServer(discard, '[::1]:50063');
+await startServer(preconditionPanic, '[::1]:50064');
@@ -2,6 +2,7 @@ import * as grpc from '@grpc/grpc-js';
 import * as protoLoader from '@grpc/proto-loader';
 import { encode, decode } from '@msgpack/msgpack';
 import path from 'path';
+import { AsyncLocalStorage } from 'async_hooks';
 import { fileURLToPath } from 'url';
 
 const __filename = fileURLToPath(import.meta.url);
@@ -19,6 +20,11 @@ const packageDefinition = protoLoader.loadSync(PROTO_PATH, {
 
 const pbtProto = grpc.loadPackageDefinition(packageDefinition) as any;
 
+// 本 runner 支持的协议版本范围，与 Rust 的 quickcheck_rpc::version 保持一致
+const MIN_PROTOCOL_VERSION = 1;
+const PROTOCOL_VERSION = 2;
+const IMPLEMENTATION = 'quickcheck_runner_nodejs 0.1.0';
+
 // 测试状态枚举
 enum TestStatus {
   PASSED = 0,
@@ -30,46 +36,367 @@ enum TestStatus {
 interface TestFunction {
   testId: string;
   execute(args: any): any;
+  // 可选：属性是否适用于这组参数，返回 false 的用例被丢弃（INVALID_INPUT），不执行
+  precondition?(args: any): boolean;
+  // 可选：对用例分类的标签，用于 tester 端的覆盖率统计
+  labels?(args: any): string[];
+  // 可选：向各个统计表添加的值，键为表名
+  tables?(args: any): Record<string, string[]>;
+  // 可选：覆盖率要求，键为标签，值为至少要带有该标签的通过用例百分比
+  coverage?(args: any): Record<string, number>;
+  // 可选：会话开始时调用一次，用于加载数据、打开数据库连接等昂贵的准备工作
+  // 不属于任何会话的用例会单独执行一次会话的准备和清理
+  setupSession?(): void | Promise<void>;
+  // 可选：会话结束时调用一次
+  teardownSession?(): void | Promise<void>;
+  // 可选：每个用例执行前调用
+  setupCase?(): void | Promise<void>;
+  // 可选：每个用例执行后调用，无论用例是否通过
+  teardownCase?(): void | Promise<void>;
+  // 可选：由 runner 生成参数，相同的 seed 和 size 必须生成相同的参数
+  // seed 是 0 到 2^64 - 1 之间的 u64，用 bigint 表示以免丢失精度
+  generate?(seed: bigint, size: number): any;
+  // 可选：返回失败参数的更小版本，最有希望的排在前面
+  shrink?(args: any): any[];
+  // 可选：参数的 schema（JSON Schema 的子集，描述 MsgPack 编码后的值），
+  // tester 在运行前据此检查参数类型是否兼容
+  argsSchema?: object;
+  // 可选：返回值的 schema
+  returnSchema?: object;
+}
+
+// 通过 Run 流执行的用例所输出的日志
+const caseLogs = new AsyncLocalStorage<string[]>();
+
+// 在测试函数中输出日志
+// 用例通过 Run 流执行时，日志随用例结果发回 tester；否则直接打印
+function log(message: string) {
+  const logs = caseLogs.getStore();
+  if (logs) {
+    logs.push(message);
+  } else {
+    console.log(message);
+  }
+}
+
+// 执行清理钩子，忽略其中的错误，避免掩盖用例的结果
+async function teardown(hook: () => void | Promise<void>) {
+  try {
+    await hook();
+  } catch {
+    // 忽略
+  }
+}
+
+// 参数和返回值的编解码器，按请求的 encoding 选择
+interface Codec {
+  encode(value: any): Uint8Array;
+  decode(data: Uint8Array): any;
+}
+
+const msgpackCodec: Codec = { encode, decode };
+const jsonCodec: Codec = {
+  encode: value => new TextEncoder().encode(JSON.stringify(value)),
+  decode: data => JSON.parse(new TextDecoder().decode(data)),
+};
+
+// 支持的编码：MSGPACK（默认）和 JSON
+const SUPPORTED_ENCODINGS = 'MSGPACK, JSON';
+
+// 返回请求所用编码的编解码器；不支持的编码返回 undefined
+// MSGPACK_COMPACT 把结构体编码为不带字段名的数组，runner 无法还原成测试函数
+// 期望的对象，因此与 CBOR 一样不支持
+function codecFor(encoding: string): Codec | undefined {
+  switch (encoding) {
+    case 'MSGPACK':
+      return msgpackCodec;
+    case 'JSON':
+      return jsonCodec;
+    default:
+      return undefined;
+  }
+}
+
+// 收集用例的标签、统计表条目和覆盖率要求
+function classify(testFunction: TestFunction, args: any) {
+  const labels = testFunction.labels ? testFunction.labels(args) : [];
+  const tables = Object.entries(testFunction.tables ? testFunction.tables(args) : {})
+    .flatMap(([table, values]) => values.map(value => ({ table, value })));
+  const coverage = Object.entries(testFunction.coverage ? testFunction.coverage(args) : {})
+    .map(([label, percentage]) => ({ label, percentage }));
+  return { labels, tables, coverage };
 }
 
 // 创建测试函数服务器
 function createTestServer(testFunction: TestFunction) {
   const server = new grpc.Server();
+  // 进行中的会话
+  const sessions = new Set<string>();
+  let nextSession = 0;
+
+  const notFound = (testId: string) => ({
+    code: grpc.status.NOT_FOUND,
+    message: `Property '${testId}' not found. This runner only supports '${testFunction.testId}'`
+  });
+  const errorMessage = (error: unknown) => error instanceof Error ? error.message : String(error);
+  
+  // 执行一个用例，返回带有协议版本的 ExecuteResponse
+  // 请求的版本不在本 runner 支持的范围内时以 FAILED_PRECONDITION 拒绝；版本为 0 的请求来自早于该字段的 tester，按版本 1 处理
+  const executeCase = async (request: any) => {
+    const version = request.version || 1;
+    if (version < MIN_PROTOCOL_VERSION || version > PROTOCOL_VERSION) {
+      throw {
+        code: grpc.status.FAILED_PRECONDITION,
+        message: `Request uses protocol version ${version}, but this runner speaks ${MIN_PROTOCOL_VERSION} to ${PROTOCOL_VERSION}`
+      };
+    }
+    return { ...(await runCase(request)), version };
+  };
+
+  // 执行一个用例，返回 ExecuteResponse；请求无效时抛出 gRPC 错误
+  const runCase = async (request: any) => {
+    // 验证属性名称
+    if (request.test_id !== testFunction.testId) {
+      throw notFound(request.test_id);
+    }
+
+    // 验证会话
+    const inSession = request.session_id !== '';
+    if (inSession && !sessions.has(request.session_id)) {
+      throw {
+        code: grpc.status.NOT_FOUND,
+        message: `Session '${request.session_id}' not found`
+      };
+    }
+
+    // 验证编码
+    const codec = codecFor(request.encoding);
+    if (!codec) {
+      throw {
+        code: grpc.status.INVALID_ARGUMENT,
+        message: `Unsupported encoding ${request.encoding}; this runner supports ${SUPPORTED_ENCODINGS}`
+      };
+    }
+
+    // 按请求的编码解析参数，无法解析时是无效请求，而不是测试失败
+    let args: any;
+    try {
+      args = codec.decode(request.test_data);
+    } catch (error) {
+      throw {
+        code: grpc.status.INVALID_ARGUMENT,
+        message: `Failed to deserialize arguments: ${errorMessage(error)}`
+      };
+    }
+
+    // 不满足前置条件的用例直接丢弃，不执行测试函数
+    // 前置条件抛出异常时用例失败，与 Rust runner 捕获 panic 的行为一致
+    let applies: boolean;
+    try {
+      applies = !testFunction.precondition || testFunction.precondition(args);
+    } catch (error) {
+      return {
+        status: TestStatus.FAILED,
+        failure_detail: errorMessage(error),
+        return_value: null
+      };
+    }
+    if (!applies) {
+      return {
+        status: TestStatus.INVALID_INPUT,
+        failure_detail: null,
+        return_value: null
+      };
+    }
+
+    // 不属于任何会话的用例单独执行会话的准备工作
+    try {
+      if (!inSession) {
+        await testFunction.setupSession?.();
+      }
+    } catch (error) {
+      throw { code: grpc.status.INTERNAL, message: `Session setup failed: ${errorMessage(error)}` };
+    }
+
+    try {
+      await testFunction.setupCase?.();
+    } catch (error) {
+      if (!inSession) {
+        await teardown(() => testFunction.teardownSession?.());
+      }
+      throw { code: grpc.status.INTERNAL, message: `Case setup failed: ${errorMessage(error)}` };
+    }
+    
+    try {
+      // 在执行前分类，避免测试函数修改参数
+      const { labels, tables, coverage } = classify(testFunction, args);
+
+      // 执行测试函数
+      const result = await testFunction.execute(args);
+      
+      // 返回成功响应
+      return {
+        status: TestStatus.PASSED,
+        failure_detail: null,
+        return_value: codec.encode(result),
+        labels,
+        tables,
+        coverage
+      };
+    } catch (error) {
+      // 返回失败响应
+      return {
+        status: TestStatus.FAILED,
+        failure_detail: errorMessage(error),
+        return_value: null
+      };
+    } finally {
+      await teardown(() => testFunction.teardownCase?.());
+      if (!inSession) {
+        await teardown(() => testFunction.teardownSession?.());
+      }
+    }
+  };
   
   server.addService(pbtProto.pbt_service.TestRunner.service, {
-    execute: (call: any, callback: any) => {
+    // 协商协议版本：选择双方都支持的最高版本，没有共同版本时返回 0
+    handshake: (call: any, callback: any) => {
+      const { min_version, max_version } = call.request;
+      const version = Math.min(max_version, PROTOCOL_VERSION);
+      callback(null, {
+        version: version >= Math.max(min_version, MIN_PROTOCOL_VERSION) ? version : 0,
+        min_version: MIN_PROTOCOL_VERSION,
+        max_version: PROTOCOL_VERSION,
+        implementation: IMPLEMENTATION
+      });
+    },
+
+    execute: async (call: any, callback: any) => {
+      try {
+        callback(null, await executeCase(call.request));
+      } catch (error) {
+        callback(error);
+      }
+    },
+
+    // 按顺序执行一批用例，任何一个请求无效时整批返回错误
+    executeBatch: async (call: any, callback: any) => {
+      const responses = [];
+      try {
+        for (const request of call.request.requests) {
+          responses.push(await executeCase(request));
+        }
+      } catch (error) {
+        callback(error);
+        return;
+      }
+      callback(null, { responses });
+    },
+
+    // 在一个双向流上按到达顺序执行用例，返回每个用例的日志和结果
+    run: (call: any) => {
+      let queue = Promise.resolve();
+      call.on('data', (request: any) => {
+        queue = queue.then(async () => {
+          const caseId = request.case_id;
+          const logs: string[] = [];
+          let result;
+          try {
+            const response = await caseLogs.run(logs, () => executeCase(request.case));
+            result = { case_id: caseId, response };
+          } catch (error: any) {
+            // 请求无效，只影响这一个用例
+            result = { case_id: caseId, error: error.message ?? String(error) };
+          }
+          for (const message of logs) {
+            call.write({ log: { case_id: caseId, message } });
+          }
+          call.write({ result });
+        });
+      });
+      call.on('end', () => {
+        queue.then(() => call.end());
+      });
+    },
+
+    generate: (call: any, callback: any) => {
       const request = call.request;
-      
-      // 验证属性名称
       if (request.test_id !== testFunction.testId) {
-        callback({
-          code: grpc.status.NOT_FOUND,
-          message: `Property '${request.test_id}' not found. This runner only supports '${testFunction.testId}'`
-        });
+        callback(notFound(request.test_id));
+        return;
+      }
+      if (!testFunction.generate) {
+        callback({ code: grpc.status.UNIMPLEMENTED, message: `Property '${testFunction.testId}' does not generate its arguments` });
         return;
       }
-      
       try {
-        // 解析 MessagePack 参数
-        const args = decode(request.test_data);
-        
-        // 执行测试函数
-        const result = testFunction.execute(args);
-        
-        // 返回成功响应
-        callback(null, {
-          status: TestStatus.PASSED,
-          failure_detail: null,
-          return_value: encode(result)
-        });
+        const args = testFunction.generate(BigInt(request.seed), Number(request.size));
+        callback(null, { test_data: encode(args) });
       } catch (error) {
-        // 返回失败响应
-        callback(null, {
-          status: TestStatus.FAILED,
-          failure_detail: error instanceof Error ? error.message : String(error),
-          return_value: null
-        });
+        callback({ code: grpc.status.INTERNAL, message: `Failed to generate arguments: ${errorMessage(error)}` });
+      }
+    },
+
+    shrink: (call: any, callback: any) => {
+      const request = call.request;
+      if (request.test_id !== testFunction.testId) {
+        callback(notFound(request.test_id));
+        return;
+      }
+      if (!testFunction.generate) {
+        callback({ code: grpc.status.UNIMPLEMENTED, message: `Property '${testFunction.testId}' does not generate its arguments` });
+        return;
+      }
+      try {
+        const candidates = testFunction.shrink ? testFunction.shrink(decode(request.test_data)) : [];
+        callback(null, { candidates: candidates.map(candidate => encode(candidate)) });
+      } catch (error) {
+        callback({ code: grpc.status.INTERNAL, message: `Failed to shrink arguments: ${errorMessage(error)}` });
+      }
+    },
+
+    describe: (call: any, callback: any) => {
+      const request = call.request;
+      if (request.test_id !== testFunction.testId) {
+        callback(notFound(request.test_id));
+        return;
+      }
+      if (!testFunction.argsSchema) {
+        callback({ code: grpc.status.UNIMPLEMENTED, message: `Property '${testFunction.testId}' does not describe its arguments` });
+        return;
+      }
+      const response: any = { args_schema: encode(testFunction.argsSchema) };
+      if (testFunction.returnSchema) {
+        response.return_schema = encode(testFunction.returnSchema);
+      }
+      callback(null, response);
+    },
+
+    beginSession: async (call: any, callback: any) => {
+      const request = call.request;
+      if (request.test_id !== testFunction.testId) {
+        callback(notFound(request.test_id));
+        return;
+      }
+      try {
+        await testFunction.setupSession?.();
+      } catch (error) {
+        callback({ code: grpc.status.INTERNAL, message: `Failed to begin session: ${errorMessage(error)}` });
+        return;
+      }
+      const sessionId = `${testFunction.testId}-${nextSession++}`;
+      sessions.add(sessionId);
+      callback(null, { session_id: sessionId });
+    },
+
+    endSession: async (call: any, callback: any) => {
+      const sessionId = call.request.session_id;
+      if (!sessions.delete(sessionId)) {
+        callback({ code: grpc.status.NOT_FOUND, message: `Session '${sessionId}' not found` });
+        return;
       }
+      await teardown(() => testFunction.teardownSession?.());
+      callback(null, {});
     }
   });
   
@@ -137,4 +464,4 @@ if (import.meta.main) {
 }
 
 // 导出类型和函数供外部使用
-export { TestFunction, TestStatus, createTestServer };
+export { TestFunction, TestStatus, createTestServer, log };
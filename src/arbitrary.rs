@@ -34,7 +34,7 @@ use rand::{Rng, SeedableRng};
 /// It is unspecified whether this is a secure RNG or not. Therefore, callers
 /// should assume it is insecure.
 pub struct Gen {
-    rng: rand::rngs::SmallRng,
+    rng: ChoiceRng,
     size: usize,
 }
 
@@ -47,7 +47,18 @@ impl Gen {
     /// randomly generated number. (Unless that number is used to control the
     /// size of a data structure.)
     pub fn new(size: usize) -> Gen {
-        Gen { rng: rand::rngs::SmallRng::from_os_rng(), size }
+        Gen { rng: ChoiceRng::new(None), size }
+    }
+
+    /// Returns a `Gen` that replays a previously recorded choice buffer.
+    ///
+    /// Every random draw is read from `choices` instead of a PRNG, so
+    /// generating values of the same type with the same size reproduces the
+    /// values that were generated when the buffer was recorded. Once the
+    /// buffer is exhausted, every further draw is zero, which generally
+    /// produces the simplest value of a type.
+    pub fn from_choices(size: usize, choices: Vec<u8>) -> Gen {
+        Gen { rng: ChoiceRng::new(Some(choices)), size }
     }
 
     /// Returns the size configured with this generator.
@@ -55,6 +66,26 @@ impl Gen {
         self.size
     }
 
+    /// Sets the size of values generated from now on.
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    /// Starts recording every random draw into a fresh choice buffer.
+    ///
+    /// Any buffer recorded so far is discarded.
+    pub fn start_recording(&mut self) {
+        self.rng.recorded = Some(vec![]);
+    }
+
+    /// Stops recording and returns the choice buffer recorded since the last
+    /// call to `start_recording`.
+    ///
+    /// If this generator is not recording, then `None` is returned.
+    pub fn take_choices(&mut self) -> Option<Vec<u8>> {
+        self.rng.recorded.take()
+    }
+
     /// Choose among the possible alternatives in the slice given. If the slice
     /// is empty, then `None` is returned. Otherwise, a non-`None` value is
     /// guaranteed to be returned.
@@ -69,7 +100,7 @@ impl Gen {
         self.rng.random()
     }
 
-    fn random_range<T, R>(&mut self, range: R) -> T
+    pub(crate) fn random_range<T, R>(&mut self, range: R) -> T
     where
         T: rand::distr::uniform::SampleUniform,
         R: rand::distr::uniform::SampleRange<T>,
@@ -78,6 +109,56 @@ impl Gen {
     }
 }
 
+/// The source of randomness behind a `Gen`.
+///
+/// Draws come either from a PRNG or from a choice buffer being replayed.
+/// Either way, they may also be appended to a recorded choice buffer, which
+/// can later be replayed with `Gen::from_choices`.
+struct ChoiceRng {
+    rng: rand::rngs::SmallRng,
+    replay: Option<(Vec<u8>, usize)>,
+    recorded: Option<Vec<u8>>,
+}
+
+impl ChoiceRng {
+    fn new(replay: Option<Vec<u8>>) -> ChoiceRng {
+        ChoiceRng {
+            rng: rand::rngs::SmallRng::from_os_rng(),
+            replay: replay.map(|choices| (choices, 0)),
+            recorded: None,
+        }
+    }
+}
+
+impl RngCore for ChoiceRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        match self.replay {
+            Some((ref choices, ref mut pos)) => {
+                let n = std::cmp::min(dst.len(), choices.len() - *pos);
+                dst[..n].copy_from_slice(&choices[*pos..*pos + n]);
+                dst[n..].fill(0);
+                *pos += n;
+            }
+            None => self.rng.fill_bytes(dst),
+        }
+        if let Some(ref mut recorded) = self.recorded {
+            recorded.extend_from_slice(dst);
+        }
+    }
+}
+
 /// Creates a shrinker with zero elements.
 pub fn empty_shrinker<A: 'static>() -> Box<dyn Iterator<Item = A>> {
     Box::new(empty())
@@ -88,6 +169,24 @@ pub fn single_shrinker<A: 'static>(value: A) -> Box<dyn Iterator<Item = A>> {
     Box::new(once(value))
 }
 
+/// Generates `n` values of type `A` with a fresh `Gen` of the given size.
+///
+/// This is useful for inspecting what a generator actually produces, for
+/// example to check the distribution of lengths or variants of a type before
+/// using it in a property.
+///
+/// # Example
+///
+/// ```rust
+/// let xs = quickcheck::sample::<Vec<u8>>(10, 100);
+/// assert_eq!(xs.len(), 100);
+/// assert!(xs.iter().all(|x| x.len() < 10));
+/// ```
+pub fn sample<A: Arbitrary>(size: usize, n: usize) -> Vec<A> {
+    let mut g = Gen::new(size);
+    (0..n).map(|_| A::arbitrary(&mut g)).collect()
+}
+
 /// `Arbitrary` describes types whose values can be randomly generated and
 /// shrunk.
 ///
@@ -1665,4 +1764,24 @@ mod test {
             ],
         );
     }
+
+    #[test]
+    fn choices_replay() {
+        let mut g = Gen::new(100);
+        g.start_recording();
+        let xs = <(Vec<u32>, String, f64, char)>::arbitrary(&mut g);
+        let choices = g.take_choices().unwrap();
+        assert!(g.take_choices().is_none());
+
+        let mut replay = Gen::from_choices(100, choices);
+        let ys = <(Vec<u32>, String, f64, char)>::arbitrary(&mut replay);
+        assert_eq!(format!("{:?}", xs), format!("{:?}", ys));
+    }
+
+    #[test]
+    fn choices_exhausted() {
+        let mut g = Gen::from_choices(100, vec![]);
+        assert_eq!(Vec::<u32>::arbitrary(&mut g), Vec::<u32>::new());
+        assert_eq!(String::arbitrary(&mut g), "");
+    }
 }
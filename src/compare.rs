@@ -0,0 +1,228 @@
+//! Ready-made comparison functions for `CompositeTest`.
+//!
+//! Every function here returns a comparison that can be passed to
+//! `CompositeTest::new` or `CompositeTest::comparing`, or named after a `;`
+//! in `quickcheck_composite!`:
+//!
+//! ```rust,ignore
+//! quickcheck_composite!(rust, node, python; all_equal);
+//! quickcheck_composite!(rust, node; approx_equal(1e-9));
+//! ```
+
+use crate::composite::Outcome;
+
+/// Passes if every implementation returned the same value.
+pub fn all_equal<A, R: PartialEq>() -> impl Fn(&A, &[R]) -> bool {
+    all_equal_by(R::eq)
+}
+
+/// Passes if every implementation returned a value equal to the first one
+/// under `eq`.
+pub fn all_equal_by<A, R, E>(eq: E) -> impl Fn(&A, &[R]) -> bool
+where
+    E: Fn(&R, &R) -> bool,
+{
+    move |_, results| match results.split_first() {
+        Some((first, rest)) => rest.iter().all(|other| eq(first, other)),
+        None => true,
+    }
+}
+
+/// Passes if more than half of the implementations returned the same value.
+///
+/// The case fails if no value has a strict majority. Implementations that
+/// disagree with the majority on a passing case are flagged by composite
+/// tests built with `CompositeTest::flag_dissenters`, which
+/// `quickcheck_composite!` does for `majority`.
+pub fn majority<A, R: PartialEq>() -> impl Fn(&A, &[R]) -> bool {
+    move |_, results| {
+        results.iter().any(|candidate| {
+            let votes = results.iter().filter(|&r| r == candidate).count();
+            2 * votes > results.len()
+        })
+    }
+}
+
+/// Passes if every other implementation is related to the first one, the
+/// reference, under `relation(reference, other)`.
+///
+/// Unlike `all_equal_by`, the relation does not need to be symmetric. For
+/// example, it can check that every other result is a subset of the
+/// reference's.
+pub fn reference<A, R, E>(relation: E) -> impl Fn(&A, &[R]) -> bool
+where
+    E: Fn(&R, &R) -> bool,
+{
+    move |_, results| match results.split_first() {
+        Some((reference, others)) => {
+            others.iter().all(|other| relation(reference, other))
+        }
+        None => true,
+    }
+}
+
+/// Passes if every implementation returned a value approximately equal to
+/// the first one, as defined by `ApproxEq`.
+pub fn approx_equal<A, R: ApproxEq>(
+    tolerance: f64,
+) -> impl Fn(&A, &[R]) -> bool {
+    all_equal_by(move |a: &R, b: &R| a.approx_eq(b, tolerance))
+}
+
+/// Passes if every implementation returned the same elements, in any order.
+///
+/// Duplicates are significant: `[1, 1, 2]` and `[1, 2, 2]` are not equal.
+pub fn unordered_equal<A, T: PartialEq>() -> impl Fn(&A, &[Vec<T>]) -> bool {
+    all_equal_by(|a: &Vec<T>, b: &Vec<T>| {
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut used = vec![false; b.len()];
+        a.iter().all(|x| {
+            match (0..b.len()).find(|&j| !used[j] && b[j] == *x) {
+                Some(j) => {
+                    used[j] = true;
+                    true
+                }
+                None => false,
+            }
+        })
+    })
+}
+
+/// Passes if every implementation returned the same value after passing it
+/// through `normalise`.
+///
+/// This is useful when implementations are allowed to differ in ways the
+/// property does not care about, such as letter case or trailing whitespace.
+pub fn equal_modulo<A, R, N, F>(normalise: F) -> impl Fn(&A, &[R]) -> bool
+where
+    N: PartialEq,
+    F: Fn(&R) -> N,
+{
+    all_equal_by(move |a, b| normalise(a) == normalise(b))
+}
+
+/// Passes if every implementation had the same kind of outcome: all of them
+/// returned a value, all of them failed, or all of them discarded the case.
+///
+/// When every implementation returned, the values are checked with `values`.
+/// Failure details are not compared, since runners in different languages
+/// rarely word their errors the same way.
+pub fn same_outcome<A, R, C>(values: C) -> impl Fn(&A, &[Outcome<R>]) -> bool
+where
+    R: Clone,
+    C: Fn(&A, &[R]) -> bool,
+{
+    move |args, outcomes| {
+        if !outcomes.windows(2).all(|pair| pair[0].same_kind(&pair[1])) {
+            return false;
+        }
+        let returned: Vec<R> =
+            outcomes.iter().filter_map(Outcome::returned).cloned().collect();
+        returned.is_empty() || values(args, &returned)
+    }
+}
+
+/// Equality up to a floating point tolerance.
+pub trait ApproxEq {
+    /// Returns `true` if `self` and `other` differ by at most `tolerance`,
+    /// relative to the larger of their magnitudes when that is above `1`.
+    ///
+    /// Two NaNs are considered equal, since implementations that agree on
+    /// producing NaN do agree.
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool;
+}
+
+impl ApproxEq for f64 {
+    fn approx_eq(&self, other: &f64, tolerance: f64) -> bool {
+        if self.is_nan() || other.is_nan() {
+            return self.is_nan() && other.is_nan();
+        }
+        if self == other {
+            return true;
+        }
+        let scale = self.abs().max(other.abs()).max(1.0);
+        (self - other).abs() <= tolerance * scale
+    }
+}
+
+impl ApproxEq for f32 {
+    fn approx_eq(&self, other: &f32, tolerance: f64) -> bool {
+        (*self as f64).approx_eq(&(*other as f64), tolerance)
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Option<T> {
+    fn approx_eq(&self, other: &Option<T>, tolerance: f64) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.approx_eq(b, tolerance),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Vec<T> {
+    fn approx_eq(&self, other: &Vec<T>, tolerance: f64) -> bool {
+        self.len() == other.len()
+            && self.iter().zip(other).all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn equality() {
+        assert!(all_equal()(&(), &[1, 1, 1]));
+        assert!(!all_equal()(&(), &[1, 1, 2]));
+        assert!(all_equal::<(), i32>()(&(), &[]));
+
+        assert!(majority()(&(), &[1, 2, 1]));
+        assert!(!majority()(&(), &[1, 2, 3]));
+        assert!(!majority()(&(), &[1, 1, 2, 2]));
+
+        let subset = reference(|r: &Vec<i32>, o: &Vec<i32>| {
+            o.iter().all(|x| r.contains(x))
+        });
+        assert!(subset(&(), &[vec![1, 2, 3], vec![2], vec![]]));
+        assert!(!subset(&(), &[vec![2], vec![1, 2, 3]]));
+
+        assert!(unordered_equal()(&(), &[vec![1, 2, 2], vec![2, 1, 2]]));
+        assert!(!unordered_equal()(&(), &[vec![1, 1, 2], vec![1, 2, 2]]));
+
+        let trimmed = equal_modulo(|s: &String| s.trim().to_lowercase());
+        assert!(trimmed(&(), &["Abc ".to_string(), "abc".to_string()]));
+        assert!(!trimmed(&(), &["abc".to_string(), "abd".to_string()]));
+    }
+
+    #[test]
+    fn outcomes() {
+        use crate::composite::Outcome::{Discarded, Failed, Returned};
+
+        let policy = same_outcome(all_equal());
+        assert!(policy(&(), &[Returned(1), Returned(1)]));
+        assert!(!policy(&(), &[Returned(1), Returned(2)]));
+        assert!(policy(&(), &[Failed(Some("a".to_string())), Failed(None)]));
+        assert!(policy(&(), &[Discarded::<i32>, Discarded]));
+        assert!(!policy(&(), &[Returned(1), Failed(None)]));
+        assert!(!policy(&(), &[Failed(None), Discarded]));
+    }
+
+    #[test]
+    fn approx() {
+        let close = approx_equal(1e-9);
+        assert!(close(&(), &[0.1 + 0.2, 0.3]));
+        assert!(close(&(), &[1e20, 1e20 + 1e5]));
+        assert!(!close(&(), &[1.0, 1.001]));
+        assert!(close(&(), &[f64::NAN, f64::NAN]));
+        assert!(!close(&(), &[f64::NAN, 0.0]));
+        assert!(approx_equal(1e-6)(
+            &(),
+            &[vec![Some(1.0f32)], vec![Some(1.0)]]
+        ));
+        assert!(!approx_equal(1e-6)(&(), &[vec![Some(1.0)], vec![None]]));
+    }
+}
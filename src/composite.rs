@@ -1,149 +1,631 @@
 use async_trait::async_trait;
-use std::fmt::Debug;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
 
+use crate::tester::{execute_remote, RemoteTest, TestFailure};
 use crate::{Arbitrary, TestResult};
 
 /// A composite test that compares results from multiple RemoteTest implementations
-pub struct CompositeTest<T, F>
+///
+/// The implementations only need to agree on their `Args` type. Each one may
+/// have its own `Return` type, which is converted into the common type `R`
+/// before the comparison sees it. This makes it possible to compare, say, a
+/// Rust runner returning `Vec<i32>` with a Node.js runner returning
+/// `Vec<f64>`.
+pub struct CompositeTest<A, R, F>
 where
-    T: crate::tester::RemoteTest + Send + Sync,
-    F: Fn(&T::Args, &[T::Return]) -> bool + Send + Sync + 'static,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
 {
-    tests: Vec<T>,
+    tests: Vec<Box<dyn Implementation<A, R>>>,
     comparison: F,
+    flag_dissenters: bool,
 }
 
-impl<T, F> CompositeTest<T, F>
+impl<A, R, F> CompositeTest<A, R, F>
 where
-    T: crate::tester::RemoteTest + Send + Sync,
-    F: Fn(&T::Args, &[T::Return]) -> bool + Send + Sync + 'static,
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
 {
-    pub fn new(tests: Vec<T>, comparison: F) -> Self {
-        Self {
-            tests,
-            comparison,
+    /// Creates a composite test over implementations of one `RemoteTest`
+    /// type.
+    pub fn new<T>(tests: Vec<T>, comparison: F) -> Self
+    where
+        T: RemoteTest<Args = A, Return = R> + 'static,
+    {
+        tests
+            .into_iter()
+            .fold(Self::comparing(comparison), |composite, test| {
+                composite.implementation(test)
+            })
+    }
+
+    /// Creates a composite test with no implementations yet.
+    ///
+    /// Add implementations with `implementation` or `implementation_with`.
+    pub fn comparing(comparison: F) -> Self {
+        Self { tests: vec![], comparison, flag_dissenters: false }
+    }
+
+    /// Labels every passing case with `dissent: ` followed by the name of
+    /// each implementation that disagrees with a strict majority, as listed
+    /// by `ComparisonReport::dissenters`.
+    ///
+    /// This is meant for `compare::majority`, and `quickcheck_composite!`
+    /// turns it on when that comparison is named.
+    pub fn flag_dissenters(mut self) -> Self {
+        self.flag_dissenters = true;
+        self
+    }
+
+    /// Adds an implementation whose return value converts into `R`.
+    pub fn implementation<T>(self, test: T) -> Self
+    where
+        T: RemoteTest<Args = A> + 'static,
+        T::Return: Into<R>,
+    {
+        self.implementation_with(test, Into::into)
+    }
+
+    /// Adds an implementation, converting its return value into `R` with the
+    /// given function.
+    pub fn implementation_with<T, M>(mut self, test: T, map: M) -> Self
+    where
+        T: RemoteTest<Args = A> + 'static,
+        M: Fn(T::Return) -> R + Send + Sync + 'static,
+    {
+        self.tests.push(Box::new(Remote { test, map, _return: PhantomData }));
+        self
+    }
+
+    /// Adds a local Rust function as an implementation.
+    ///
+    /// The function is called in-process on every case, so a trusted model
+    /// can be compared with a remote implementation without a second runner.
+    /// It is reported as `oracle @ local`.
+    pub fn oracle<M>(mut self, model: M) -> Self
+    where
+        M: Fn(&A) -> R + Send + Sync + 'static,
+    {
+        self.tests.push(Box::new(Local { name: "oracle", model }));
+        self
+    }
+}
+
+impl<A, R, F> CompositeTest<A, Outcome<R>, F>
+where
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+    F: Fn(&A, &[Outcome<R>]) -> bool + Send + Sync + 'static,
+{
+    /// Creates a composite test that compares the outcomes of
+    /// implementations of one `RemoteTest` type.
+    ///
+    /// Unlike `new`, a failing or discarding implementation does not end the
+    /// case. Its outcome is handed to the comparison along with everyone
+    /// else's, so that "every implementation rejects this input" can count as
+    /// agreement. See `compare::same_outcome` for a ready-made policy.
+    pub fn new_outcomes<T>(tests: Vec<T>, comparison: F) -> Self
+    where
+        T: RemoteTest<Args = A, Return = R> + 'static,
+    {
+        tests
+            .into_iter()
+            .fold(Self::comparing(comparison), |composite, test| {
+                composite.outcome_of(test)
+            })
+    }
+
+    /// Adds an implementation whose outcome is compared, and whose return
+    /// value converts into `R`.
+    pub fn outcome_of<T>(self, test: T) -> Self
+    where
+        T: RemoteTest<Args = A> + 'static,
+        T::Return: Into<R>,
+    {
+        self.outcome_of_with(test, Into::into)
+    }
+
+    /// Adds an implementation whose outcome is compared, converting its
+    /// return value into `R` with the given function.
+    pub fn outcome_of_with<T, M>(mut self, test: T, map: M) -> Self
+    where
+        T: RemoteTest<Args = A> + 'static,
+        M: Fn(T::Return) -> R + Send + Sync + 'static,
+    {
+        let remote = Remote { test, map, _return: PhantomData };
+        self.tests.push(Box::new(Outcomes(remote)));
+        self
+    }
+}
+
+/// How one implementation of a composite test handled a case.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Outcome<R> {
+    /// The implementation passed and returned a value.
+    Returned(R),
+    /// The implementation failed, with the runner's failure detail.
+    Failed(Option<String>),
+    /// The implementation discarded the case.
+    Discarded,
+}
+
+impl<R> Outcome<R> {
+    /// Returns the value, if the implementation returned one.
+    pub fn returned(&self) -> Option<&R> {
+        match self {
+            Outcome::Returned(value) => Some(value),
+            _ => None,
         }
     }
+
+    /// Returns `true` if `self` and `other` are the same variant, regardless
+    /// of the value or failure detail they hold.
+    pub fn same_kind(&self, other: &Outcome<R>) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
 }
 
+/// A single implementation of a composite test, with its return type erased.
 #[async_trait]
-impl<T, F> crate::tester::Testable for CompositeTest<T, F>
+trait Implementation<A, R>: Send + Sync {
+    /// Returns the ID of the test in its runner.
+    fn test_id(&self) -> &'static str;
+
+    /// Returns the address of the runner.
+    fn endpoint(&self) -> &str;
+
+    /// Executes the implementation once on `args`, without shrinking, and
+    /// decodes its result into the common type, along with what the
+    /// implementation tagged the case with.
+    async fn execute(&self, args: &A) -> (Decoded<R>, Tags);
+}
+
+/// The labels, table values and coverage requirements an implementation
+/// attached to a case.
+#[derive(Default)]
+struct Tags {
+    labels: Vec<String>,
+    tables: Vec<(String, String)>,
+    coverage: Vec<(String, f64)>,
+}
+
+impl Tags {
+    fn of(result: &TestResult) -> Tags {
+        Tags {
+            labels: result.labels.clone(),
+            tables: result.tables.clone(),
+            coverage: result.coverage.clone(),
+        }
+    }
+
+    /// Adds these tags to the result of a composite case.
+    fn add_to(self, result: &mut TestResult) {
+        result.labels.extend(self.labels);
+        result.tables.extend(self.tables);
+        result.coverage.extend(self.coverage);
+    }
+}
+
+/// A decoded result of one implementation.
+enum Decoded<R> {
+    Value(R),
+    Failed(TestFailure),
+    Discarded,
+    /// The implementation could not be run, or its result not decoded.
+    Error(String),
+}
+
+struct Remote<T, M, R> {
+    test: T,
+    map: M,
+    _return: PhantomData<fn() -> R>,
+}
+
+#[async_trait]
+impl<T, M, R> Implementation<T::Args, R> for Remote<T, M, R>
 where
-    T: crate::tester::RemoteTest + Send + Sync + 'static,
-    T::Args: Arbitrary + Debug + Clone + Send + Sync,
-    F: Fn(&T::Args, &[T::Return]) -> bool + Send + Sync + 'static,
+    T: RemoteTest + 'static,
+    M: Fn(T::Return) -> R + Send + Sync + 'static,
+    R: 'static,
 {
-    type Args = T::Args;
-    
-    async fn result(&self, args: &Self::Args) -> TestResult {
-        async fn execute_tests<T: crate::tester::RemoteTest + 'static>(
-            tests: &[T],
-            args: &T::Args,
-        ) -> Result<Vec<T::Return>, TestResult> {
-            /// Helper function to extract the return value from a TestResult
-            fn extract_return_value<T: crate::tester::RemoteTest>(result: &TestResult) -> Result<T::Return, String> {
-                if let Some(ref msgpack) = result.return_value {
-                    rmp_serde::from_slice(msgpack)
-                        .map_err(|e| format!("Failed to deserialize return value: {}", e))
-                } else {
-                    Err("No return value available".to_string())
+    fn test_id(&self) -> &'static str {
+        T::TEST_ID
+    }
+
+    fn endpoint(&self) -> &str {
+        self.test.endpoint()
+    }
+
+    async fn execute(&self, args: &T::Args) -> (Decoded<R>, Tags) {
+        let result = match execute_remote(&self.test, args).await {
+            Ok(result) => result,
+            Err(e) => {
+                let e = format!("Tester failed to call runner: {}", e);
+                return (Decoded::Error(e), Tags::default());
+            }
+        };
+        let decoded = match result.status {
+            crate::tester::Status::Pass => {
+                match extract_return_value(&self.test, &result) {
+                    Ok(value) => Decoded::Value((self.map)(value)),
+                    Err(e) => Decoded::Error(format!(
+                        "Failed to extract return values: {}",
+                        e
+                    )),
                 }
             }
+            crate::tester::Status::Discard => Decoded::Discarded,
+            crate::tester::Status::Fail => Decoded::Failed(
+                result.failure.clone().unwrap_or(TestFailure::Property(None)),
+            ),
+        };
+        (decoded, Tags::of(&result))
+    }
+}
 
-            let mut results = Vec::new();
-            for test in tests {
-                let result = test.result(args).await;
-                if result.is_failure() {
-                    return Err(TestResult {
-                        status: crate::tester::Status::Fail,
-                        arguments: vec![format!("{:?}", args)],
-                        failure: result.failure, // Propagate the failure
-                        return_value: None,
-                    });
-                }
-                results.push(result);
+/// A local Rust function standing in for an implementation.
+struct Local<M> {
+    name: &'static str,
+    model: M,
+}
+
+#[async_trait]
+impl<A, R, M> Implementation<A, R> for Local<M>
+where
+    A: Sync,
+    M: Fn(&A) -> R + Send + Sync,
+{
+    fn test_id(&self) -> &'static str {
+        self.name
+    }
+
+    fn endpoint(&self) -> &str {
+        "local"
+    }
+
+    async fn execute(&self, args: &A) -> (Decoded<R>, Tags) {
+        (Decoded::Value((self.model)(args)), Tags::default())
+    }
+}
+
+/// Wraps an implementation so that failures and discards become values.
+struct Outcomes<I>(I);
+
+#[async_trait]
+impl<A, R, I> Implementation<A, Outcome<R>> for Outcomes<I>
+where
+    A: Sync,
+    I: Implementation<A, R>,
+{
+    fn test_id(&self) -> &'static str {
+        self.0.test_id()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.0.endpoint()
+    }
+
+    async fn execute(&self, args: &A) -> (Decoded<Outcome<R>>, Tags) {
+        let (decoded, tags) = self.0.execute(args).await;
+        let decoded = match decoded {
+            Decoded::Value(value) => Decoded::Value(Outcome::Returned(value)),
+            Decoded::Failed(TestFailure::Property(detail)) => {
+                Decoded::Value(Outcome::Failed(detail))
             }
-            
-            let mut return_values = Vec::new();
-            for result in &results {
-                match extract_return_value::<T>(result) {
-                    Ok(value) => return_values.push(value),
-                    Err(e) => {
-                        return Err(TestResult {
-                            status: crate::tester::Status::Fail,
-                            arguments: vec![format!("{:?}", args)],
-                            failure: Some(crate::tester::TestFailure::Runtime(format!("Failed to extract return values: {}", e))),
-                            return_value: None,
-                        });
-                    }
-                }
+            Decoded::Failed(failure) => {
+                Decoded::Value(Outcome::Failed(Some(format!("{:?}", failure))))
+            }
+            Decoded::Discarded => Decoded::Value(Outcome::Discarded),
+            Decoded::Error(e) => Decoded::Error(e),
+        };
+        (decoded, tags)
+    }
+}
+
+#[async_trait]
+impl<A, R, F> crate::tester::Testable for CompositeTest<A, R, F>
+where
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
+{
+    type Args = A;
+
+    fn test_id(&self) -> Option<String> {
+        let mut ids: Vec<&str> = vec![];
+        for test in &self.tests {
+            if !ids.contains(&test.test_id()) {
+                ids.push(test.test_id());
             }
-            Ok(return_values)
         }
+        Some(format!("{}.composite", ids.join("+")))
+    }
 
-        async fn shrink_failure<T, F>( // Correctly placed inside result
-            composite: &CompositeTest<T, F>,
-            initial_args: T::Args,
+    fn replays_regressions(&self) -> bool {
+        true
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<Self::Args> {
+        rmp_serde::from_slice(test_data).ok()
+    }
+
+    async fn result(&self, args: &Self::Args) -> TestResult {
+        async fn shrink_failure<A, R, F>(
+            composite: &CompositeTest<A, R, F>,
+            initial_args: A,
         ) -> Option<TestResult>
         where
-            T: crate::tester::RemoteTest + Send + Sync + 'static,
-            T::Args: Arbitrary + Debug + Clone + Send + Sync,
-            F: Fn(&T::Args, &[T::Return]) -> bool + Send + Sync + 'static,
+            A: Arbitrary + Serialize + Debug + Clone + Send + Sync + 'static,
+            R: Debug + Send + Sync + 'static,
+            F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
         {
             println!("Shrinking composite test... Args: {:?}", initial_args);
             let shrunk_values: Vec<_> = initial_args.shrink().collect();
-            
+
             for shrunk_args in shrunk_values {
-                match execute_tests(&composite.tests, &shrunk_args).await {
-                    Ok(return_values) => {
-                        if !(composite.comparison)(&shrunk_args, &return_values) {
-                            // This is a smaller failing case due to comparison.
-                            // Recurse to see if we can find an even smaller one.
-                            let smaller_failure = Box::pin(shrink_failure(composite, shrunk_args.clone())).await;
-                            if let Some(smaller_result) = smaller_failure {
-                                return Some(smaller_result);
-                            } else {
-                                // This is the smallest we could find.
-                                return Some(TestResult {
-                                    status: crate::tester::Status::Fail,
-                                    arguments: vec![format!("{:?}", shrunk_args)],
-                                    failure: Some(crate::tester::TestFailure::Comparison),
-                                    return_value: None,
-                                });
-                            }
-                        }
-                    }
-                    Err(err_result) => {
-                        // A non-comparison failure occurred during shrinking (e.g., runtime error).
-                        // This is a candidate for the smallest failure.
-                        // We should not continue shrinking, as the nature of the failure has changed.
-                        // We return this error immediately as the new smallest failure.
-                        return Some(err_result);
-                    }
+                let result = composite.check(&shrunk_args).await;
+                if result.is_error() {
+                    // A runtime error occurred during shrinking. The nature
+                    // of the failure has changed, so stop shrinking and
+                    // report it as the smallest failure.
+                    return Some(result);
+                }
+                if result.is_failure() {
+                    // This is a smaller failing case. Recurse to see if we
+                    // can find an even smaller one.
+                    let smaller_failure =
+                        Box::pin(shrink_failure(composite, shrunk_args)).await;
+                    return Some(smaller_failure.unwrap_or(result));
                 }
             }
             None
         }
 
-        match execute_tests(&self.tests, args).await {
-            Ok(return_values) => {
-                if (self.comparison)(args, &return_values) {
-                    TestResult::passed()
-                } else {
-                    // Start shrink process for failing composite test
-                    shrink_failure(self, args.clone()).await.unwrap_or_else(|| TestResult {
-                        status: crate::tester::Status::Fail,
-                        arguments: vec![format!("{:?}", args)],
-                        failure: Some(crate::tester::TestFailure::Comparison),
-                        return_value: None,
-                    })
+        let result = self.check(args).await;
+        if result.is_failure() && !result.is_error() {
+            // Start shrink process for failing composite test
+            shrink_failure(self, args.clone()).await.unwrap_or(result)
+        } else {
+            result
+        }
+    }
+}
+
+impl<A, R, F> CompositeTest<A, R, F>
+where
+    A: Serialize + Debug + Send + Sync,
+    R: Debug,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
+{
+    /// Executes every implementation exactly once on `args` and compares
+    /// their return values, without shrinking.
+    ///
+    /// Implementations are called concurrently through the raw execute path
+    /// rather than through `Testable`, so a failing implementation is never
+    /// shrunk on its own. Outcomes are inspected in the order the
+    /// implementations were added. Unless the outcomes themselves are being
+    /// compared, a failing implementation fails the case and a discarding
+    /// one discards it.
+    async fn check(&self, args: &A) -> TestResult {
+        let failure = |failure| TestResult {
+            status: crate::tester::Status::Fail,
+            arguments: vec![format!("{:?}", args)],
+            failure: Some(failure),
+            return_value: None,
+            test_data: rmp_serde::to_vec_named(args).ok(),
+            ..TestResult::default()
+        };
+
+        let results =
+            join_all(self.tests.iter().map(|test| test.execute(args))).await;
+        let mut return_values = Vec::new();
+        let mut passed = TestResult::passed();
+        for (result, tags) in results {
+            // Each implementation's tags count towards the composite case.
+            tags.add_to(&mut passed);
+            match result {
+                Decoded::Value(value) => return_values.push(value),
+                Decoded::Discarded => return TestResult::discard(),
+                // Propagate the failure
+                Decoded::Failed(f) => return failure(f),
+                Decoded::Error(e) => return failure(TestFailure::Runtime(e)),
+            }
+        }
+
+        let report = ComparisonReport {
+            returns: self
+                .tests
+                .iter()
+                .zip(&return_values)
+                .map(|(test, value)| ImplementationReturn {
+                    test_id: test.test_id().to_string(),
+                    endpoint: test.endpoint().to_string(),
+                    value: format!("{:#?}", value),
+                })
+                .collect(),
+        };
+        if (self.comparison)(args, &return_values) {
+            // Implementations outvoted on a passing case show up in the
+            // label table at the end of the run.
+            if self.flag_dissenters {
+                for name in report.dissenters() {
+                    passed = passed.label(format!("dissent: {}", name));
                 }
             }
-            Err(result) => result,
+            passed
+        } else {
+            failure(TestFailure::Comparison(report))
         }
     }
 }
 
+/// Helper function to extract the return value from a TestResult
+fn extract_return_value<T: RemoteTest>(
+    test: &T,
+    result: &TestResult,
+) -> Result<T::Return, String> {
+    if let Some(ref return_value) = result.return_value {
+        test.codec()
+            .decode(return_value)
+            .map_err(|e| format!("Failed to deserialize return value: {}", e))
+    } else {
+        Err("No return value available".to_string())
+    }
+}
+
+/// What each implementation of a composite test returned on a case where the
+/// comparison function returned false.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ComparisonReport {
+    /// The return value of every implementation, in the order they were
+    /// added to the composite.
+    pub returns: Vec<ImplementationReturn>,
+}
+
+/// The decoded return value of one implementation of a composite test.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImplementationReturn {
+    /// The ID of the test in its runner.
+    pub test_id: String,
+    /// The address of the runner.
+    pub endpoint: String,
+    /// The return value, converted to the common type and pretty-printed
+    /// with `{:#?}`.
+    pub value: String,
+}
+
+impl ComparisonReport {
+    /// Groups the implementations that returned the same value, as indices
+    /// into `returns`.
+    ///
+    /// The largest group comes first. Groups of equal size keep the order in
+    /// which their first member was added.
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for (i, ret) in self.returns.iter().enumerate() {
+            match groups
+                .iter_mut()
+                .find(|group| self.returns[group[0]].value == ret.value)
+            {
+                Some(group) => group.push(i),
+                None => groups.push(vec![i]),
+            }
+        }
+        groups.sort_by_key(|group| cmp::Reverse(group.len()));
+        groups
+    }
+
+    /// Names the implementations that disagree with a strict majority, as
+    /// `TEST_ID @ ENDPOINT`.
+    ///
+    /// A composite test built with `CompositeTest::flag_dissenters` labels
+    /// a passing case with `dissent: ` followed by each of these names, so
+    /// that an implementation outvoted by `compare::majority` is flagged in
+    /// the label table at the end of the run. Without a strict majority, no implementation is a dissenter.
+    /// Values are told apart by their printed form, so an implementation
+    /// that a looser comparison such as `approx_equal` accepts can still be
+    /// flagged.
+    pub fn dissenters(&self) -> Vec<String> {
+        let groups = self.groups();
+        match groups.split_first() {
+            Some((majority, others))
+                if 2 * majority.len() > self.returns.len() =>
+            {
+                let mut dissenters: Vec<usize> =
+                    others.iter().flatten().copied().collect();
+                dissenters.sort();
+                dissenters
+                    .into_iter()
+                    .map(|i| self.returns[i].name())
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl ImplementationReturn {
+    /// Names the implementation as `TEST_ID @ ENDPOINT`.
+    pub fn name(&self) -> String {
+        format!("{} @ {}", self.test_id, self.endpoint)
+    }
+}
+
+impl fmt::Display for ComparisonReport {
+    /// Prints every group of agreeing implementations with its value, and a
+    /// line diff of every other group's value against the largest group.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Comparison function returned false")?;
+        let groups = self.groups();
+        let reference = match groups.first() {
+            Some(group) => &self.returns[group[0]].value,
+            None => return Ok(()),
+        };
+        for (n, group) in groups.iter().enumerate() {
+            let names: Vec<String> =
+                group.iter().map(|&i| self.returns[i].name()).collect();
+            write!(f, "\n  Group {} ({}):", n + 1, names.join(", "))?;
+            let value = &self.returns[group[0]].value;
+            if n == 0 {
+                for line in value.lines() {
+                    write!(f, "\n      {}", line)?;
+                }
+            } else {
+                write!(f, "\n    Diff against group 1:")?;
+                for (tag, line) in diff_lines(reference, value) {
+                    write!(f, "\n    {} {}", tag, line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Computes a line diff from `old` to `new` as `(tag, line)` pairs, where the
+/// tag is `' '` for common lines, `'-'` for removed lines and `'+'` for added
+/// lines.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len()
+            && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j])
+        {
+            diff.push(('+', new[j]));
+            j += 1;
+        } else {
+            diff.push(('-', old[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
 /// Macro for creating composite tests with arbitrary number of tests
 #[macro_export]
 macro_rules! quickcheck_composite {
@@ -201,4 +683,85 @@ macro_rules! quickcheck_composite {
     ($($tests:expr),+ , |$args:ident, $results:ident| $comparison:expr) => {
         $crate::quickcheck($crate::CompositeTest::new(vec![$($tests),+], |$args, $results| $comparison)).await
     };
+
+    // Majority voting, which also flags the outvoted implementations
+    ($($tests:expr),+ ; majority) => {
+        $crate::quickcheck($crate::CompositeTest::new(vec![$($tests),+], $crate::compare::majority()).flag_dissenters()).await
+    };
+
+    // Built-in comparison from `quickcheck::compare`, named after a `;`
+    ($($tests:expr),+ ; $strategy:ident $(($($arg:expr),* $(,)?))?) => {
+        $crate::quickcheck($crate::CompositeTest::new(vec![$($tests),+], $crate::compare::$strategy($($($arg),*)?))).await
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ComparisonReport, CompositeTest, ImplementationReturn};
+    use crate::{compare, tester::TestFailure, QuickCheck};
+
+    fn ret(endpoint: &str, value: &str) -> ImplementationReturn {
+        ImplementationReturn {
+            test_id: "sort".to_string(),
+            endpoint: endpoint.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn groups_and_diff() {
+        let report = ComparisonReport {
+            returns: vec![
+                ret("a", "[\n    1,\n    2,\n]"),
+                ret("b", "[\n    2,\n    1,\n]"),
+                ret("c", "[\n    1,\n    2,\n]"),
+            ],
+        };
+        assert_eq!(report.groups(), vec![vec![0, 2], vec![1]]);
+        assert_eq!(
+            report.to_string(),
+            "Comparison function returned false
+  Group 1 (sort @ a, sort @ c):
+      [
+          1,
+          2,
+      ]
+  Group 2 (sort @ b):
+    Diff against group 1:
+      [
+    +     2,
+          1,
+    -     2,
+      ]"
+        );
+    }
+
+    #[test]
+    fn dissenters() {
+        let report = ComparisonReport {
+            returns: vec![ret("a", "1"), ret("b", "2"), ret("c", "1")],
+        };
+        assert_eq!(report.dissenters(), vec!["sort @ b".to_string()]);
+        let report = ComparisonReport {
+            returns: vec![ret("a", "1"), ret("b", "2")],
+        };
+        assert!(report.dissenters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn oracles_are_compared_and_shrunk() {
+        let composite = CompositeTest::comparing(compare::all_equal())
+            .oracle(|xs: &Vec<u8>| xs.len())
+            .oracle(|xs: &Vec<u8>| xs.iter().filter(|&&x| x != 7).count());
+        let mut qc = QuickCheck::new();
+        let r = qc.quicktest(composite).await.unwrap_err();
+        assert_eq!(r.arguments, vec!["[7]".to_string()]);
+        let report = match r.failure {
+            Some(TestFailure::Comparison(report)) => report,
+            failure => panic!("unexpected failure: {:?}", failure),
+        };
+        assert_eq!(report.returns[0].endpoint, "local");
+        assert_eq!(report.returns[0].value, "1");
+        assert_eq!(report.returns[1].value, "0");
+    }
 }
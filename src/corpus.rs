@@ -0,0 +1,205 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::IgnoredAny;
+
+use crate::database::escape_test_id;
+
+/// An on-disk corpus of minimal failing arguments for remote tests.
+///
+/// Each property gets one file, `<dir>/<TEST_ID>.msgpack`, holding a plain
+/// concatenation of MessagePack values. Every value is encoded like
+/// `ExecuteRequest.test_data` in the default `MSGPACK` encoding, whatever
+/// codec the failing run used, so a runner in any language can replay the
+/// corpus on its own by decoding values one after another and feeding each
+/// to its test function.
+#[derive(Clone, Debug)]
+pub struct RegressionCorpus {
+    dir: PathBuf,
+}
+
+impl RegressionCorpus {
+    /// Returns a corpus rooted at the given directory.
+    ///
+    /// The directory is created lazily the first time an entry is appended.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> RegressionCorpus {
+        RegressionCorpus { dir: dir.into() }
+    }
+
+    /// Returns the directory this corpus is rooted at.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the corpus file for the given test ID.
+    pub fn path(&self, test_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.msgpack", escape_test_id(test_id)))
+    }
+
+    /// Returns every entry of the corpus file for the given test ID, in the
+    /// order they were appended.
+    ///
+    /// A missing file is an empty corpus. If the file ends with a truncated
+    /// value, the entries before it are still returned.
+    pub fn load(&self, test_id: &str) -> Vec<Vec<u8>> {
+        let data = match fs::read(self.path(test_id)) {
+            Ok(data) => data,
+            Err(_) => return vec![],
+        };
+        let mut entries = vec![];
+        let mut cursor = Cursor::new(&data[..]);
+        while (cursor.position() as usize) < data.len() {
+            let start = cursor.position() as usize;
+            if rmp_serde::from_read::<_, IgnoredAny>(&mut cursor).is_err() {
+                break;
+            }
+            entries.push(data[start..cursor.position() as usize].to_vec());
+        }
+        entries
+    }
+
+    /// Appends an encoded entry to the corpus file for the given test ID.
+    ///
+    /// Appending an entry that is already in the corpus is a no-op.
+    pub fn append(&self, test_id: &str, test_data: &[u8]) -> io::Result<()> {
+        if self.load(test_id).iter().any(|entry| entry == test_data) {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(test_id))?
+            .write_all(test_data)
+    }
+
+    /// Replaces the corpus file for the given test ID with the given
+    /// entries, removing it when there are none.
+    pub fn replace(
+        &self,
+        test_id: &str,
+        entries: &[Vec<u8>],
+    ) -> io::Result<()> {
+        if entries.is_empty() {
+            return match fs::remove_file(self.path(test_id)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(test_id), entries.concat())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use super::RegressionCorpus;
+    use crate::{QuickCheck, TestResult, Testable};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Args {
+        xs: Vec<i64>,
+        name: String,
+    }
+
+    #[test]
+    fn append_load() {
+        let dir = env::temp_dir()
+            .join(format!("quickcheck-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let corpus = RegressionCorpus::new(&dir);
+        assert!(corpus.load("prop").is_empty());
+
+        let a = Args { xs: vec![1, -2, 3], name: "a".to_string() };
+        let b = Args { xs: vec![], name: "b".to_string() };
+        let a_data = rmp_serde::to_vec_named(&a).unwrap();
+        let b_data = rmp_serde::to_vec_named(&b).unwrap();
+        corpus.append("prop", &a_data).unwrap();
+        corpus.append("prop", &b_data).unwrap();
+        corpus.append("prop", &a_data).unwrap();
+        assert_eq!(corpus.path("prop"), dir.join("prop.msgpack"));
+
+        let entries = corpus.load("prop");
+        assert_eq!(entries, vec![a_data, b_data]);
+        let decoded: Args = rmp_serde::from_slice(&entries[1]).unwrap();
+        assert_eq!(decoded, b);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Fails on any vector summing to more than 1000, reporting the encoded
+    /// arguments like a remote test does.
+    struct SmallSum;
+
+    #[async_trait]
+    impl Testable for SmallSum {
+        type Args = Vec<u16>;
+
+        fn test_id(&self) -> Option<String> {
+            Some("small_sum".to_string())
+        }
+
+        fn replays_regressions(&self) -> bool {
+            true
+        }
+
+        fn decode_args(&self, test_data: &[u8]) -> Option<Vec<u16>> {
+            rmp_serde::from_slice(test_data).ok()
+        }
+
+        async fn result(&self, args: &Vec<u16>) -> TestResult {
+            let sum: u64 = args.iter().map(|&x| x as u64).sum();
+            let mut r = TestResult::from_bool(sum <= 1000);
+            r.test_data = rmp_serde::to_vec_named(args).ok();
+            r
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_are_appended_and_replayed() {
+        let dir = env::temp_dir()
+            .join(format!("quickcheck-corpus-qc-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let corpus = RegressionCorpus::new(&dir);
+
+        let mut qc = QuickCheck::new().regressions(Some(corpus.clone()));
+        let failure = qc.quicktest(SmallSum).await.unwrap_err();
+        assert_eq!(corpus.load("small_sum"), vec![failure.test_data.unwrap()]);
+
+        let mut qc = QuickCheck::new()
+            .regressions(Some(corpus.clone()))
+            .tests(0)
+            .max_tests(0);
+        assert!(qc.quicktest(SmallSum).await.is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn entries_that_no_longer_decode_are_removed() {
+        let dir = env::temp_dir()
+            .join(format!("quickcheck-corpus-stale-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let corpus = RegressionCorpus::new(&dir);
+        let passing = rmp_serde::to_vec_named(&vec![1u16, 2]).unwrap();
+        let stale = rmp_serde::to_vec_named(&"not a vector").unwrap();
+        corpus.append("small_sum", &stale).unwrap();
+        corpus.append("small_sum", &passing).unwrap();
+
+        let mut qc = QuickCheck::new()
+            .regressions(Some(corpus.clone()))
+            .tests(0)
+            .max_tests(0);
+        assert!(qc.quicktest(SmallSum).await.is_ok());
+        assert_eq!(corpus.load("small_sum"), vec![passing]);
+
+        corpus.replace("small_sum", &[]).unwrap();
+        assert!(!corpus.path("small_sum").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::TestResult;
+
+/// Label and table statistics collected over the passed cases of a run.
+///
+/// Cases are tagged with `TestResult::label`, `classify`, `collect`,
+/// `tabulate` and `cover`, or by a remote runner through the `labels`,
+/// `tables` and `coverage` fields of its `ExecuteResponse`. Only cases that
+/// pass are counted.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    tests: u64,
+    labels: BTreeMap<String, u64>,
+    tables: BTreeMap<String, BTreeMap<String, u64>>,
+    requirements: BTreeMap<String, f64>,
+}
+
+impl Coverage {
+    /// Returns the number of cases counted.
+    pub fn tests(&self) -> u64 {
+        self.tests
+    }
+
+    /// Returns the number of cases tagged with the given label.
+    pub fn count(&self, label: &str) -> u64 {
+        self.labels.get(label).copied().unwrap_or(0)
+    }
+
+    /// Returns the percentage of cases tagged with the given label.
+    pub fn percentage(&self, label: &str) -> f64 {
+        if self.tests == 0 {
+            return 0.0;
+        }
+        100.0 * self.count(label) as f64 / self.tests as f64
+    }
+
+    /// Requires that at least `pct` percent of cases are tagged with `label`.
+    ///
+    /// If several requirements are given for the same label, the largest one
+    /// wins.
+    pub fn require(&mut self, pct: f64, label: &str) {
+        let required =
+            self.requirements.entry(label.to_string()).or_insert(0.0);
+        if pct > *required {
+            *required = pct;
+        }
+    }
+
+    /// Returns every coverage requirement that is not met, as the label, the
+    /// required percentage and the actual percentage.
+    pub fn insufficient(&self) -> Vec<(String, f64, f64)> {
+        self.requirements
+            .iter()
+            .map(|(label, &required)| {
+                (label.clone(), required, self.percentage(label))
+            })
+            .filter(|&(_, required, actual)| actual < required)
+            .collect()
+    }
+
+    /// Returns `true` if no case was tagged and no table was filled.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.tables.is_empty()
+    }
+
+    /// Counts a passed case.
+    pub(crate) fn record(&mut self, result: &TestResult) {
+        self.tests += 1;
+        let mut labels: Vec<&String> = result.labels.iter().collect();
+        labels.sort();
+        labels.dedup();
+        for label in labels {
+            *self.labels.entry(label.clone()).or_insert(0) += 1;
+        }
+        for (table, value) in &result.tables {
+            let table = self.tables.entry(table.clone()).or_default();
+            *table.entry(value.clone()).or_insert(0) += 1;
+        }
+        for (label, pct) in &result.coverage {
+            self.require(*pct, label);
+        }
+    }
+
+    /// Renders the label distribution and every table, most frequent entries
+    /// first.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        if !self.labels.is_empty() {
+            let _ =
+                writeln!(out, "[quickcheck] Labels ({} tests):", self.tests);
+            write_rows(&mut out, &self.labels, self.tests);
+        }
+        for (name, table) in &self.tables {
+            let total = table.values().sum();
+            let _ = writeln!(
+                out,
+                "[quickcheck] Table {} ({} values):",
+                name, total
+            );
+            write_rows(&mut out, table, total);
+        }
+        out
+    }
+}
+
+fn write_rows(out: &mut String, counts: &BTreeMap<String, u64>, total: u64) {
+    let mut rows: Vec<(&String, &u64)> = counts.iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (name, &count) in rows {
+        let pct = 100.0 * count as f64 / total.max(1) as f64;
+        let _ = writeln!(out, "  {:5.1}% {}", pct, name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use super::Coverage;
+    use crate::{tester::TestFailure, QuickCheck, TestResult, Testable};
+
+    #[test]
+    fn record_and_report() {
+        let mut coverage = Coverage::default();
+        for x in 0..10 {
+            let r = TestResult::passed()
+                .classify(x == 0, "zero")
+                .classify(x % 2 == 0, "even")
+                .label("all")
+                .cover(50.0, x % 2 == 1, "odd")
+                .tabulate("digits", vec![x.to_string(), "any".to_string()]);
+            coverage.record(&r);
+        }
+        assert_eq!(coverage.tests(), 10);
+        assert_eq!(coverage.count("zero"), 1);
+        assert_eq!(coverage.count("even"), 5);
+        assert_eq!(coverage.count("all"), 10);
+        assert_eq!(coverage.percentage("odd"), 50.0);
+        assert!(coverage.insufficient().is_empty());
+
+        coverage.require(60.0, "odd");
+        assert_eq!(
+            coverage.insufficient(),
+            vec![("odd".to_string(), 60.0, 50.0)]
+        );
+
+        let report = coverage.report();
+        assert!(report.contains("[quickcheck] Labels (10 tests):"));
+        assert!(report.contains("  100.0% all"));
+        assert!(report.contains("   50.0% even"));
+        assert!(report.contains("   10.0% zero"));
+        assert!(report.contains("[quickcheck] Table digits (20 values):"));
+        assert!(report.contains("   50.0% any"));
+    }
+
+    /// Passes every case, labelling the ones where the vector is empty.
+    struct Empty;
+
+    #[async_trait]
+    impl Testable for Empty {
+        type Args = Vec<u8>;
+
+        async fn result(&self, args: &Vec<u8>) -> TestResult {
+            TestResult::passed().classify(args.is_empty(), "empty")
+        }
+    }
+
+    #[tokio::test]
+    async fn cover_fails_run() {
+        let mut qc = QuickCheck::new().cover(0.0, "empty");
+        assert_eq!(qc.quicktest(Empty).await.unwrap(), 100);
+        assert_eq!(qc.coverage().tests(), 100);
+
+        let mut qc = QuickCheck::new().cover(90.0, "empty");
+        let r = qc.quicktest(Empty).await.unwrap_err();
+        assert!(matches!(r.failure, Some(TestFailure::Coverage(_))));
+    }
+}
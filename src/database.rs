@@ -0,0 +1,350 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A failing example: the size of the `Gen` and the raw choice buffer it
+/// recorded while the failing arguments were generated.
+///
+/// Replaying `choices` with `Gen::from_choices(size, choices)` reproduces the
+/// arguments.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Example {
+    pub size: usize,
+    pub choices: Vec<u8>,
+}
+
+/// The bytes every example file starts with.
+const MAGIC: &[u8; 4] = b"QCEX";
+
+/// The version of the example file format, which is bumped whenever the
+/// format or the way `Gen` consumes choices changes.
+const VERSION: u8 = 1;
+
+/// The length of the magic, version and size that precede the choices.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 8;
+
+impl Example {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.push(VERSION);
+        data.extend_from_slice(&(self.size as u64).to_le_bytes());
+        data.extend_from_slice(&self.choices);
+        data
+    }
+
+    /// Decodes an example, or returns `None` if the data is not an example
+    /// written in this version of the format.
+    fn decode(data: &[u8]) -> Option<Example> {
+        if data.len() < HEADER_LEN
+            || &data[..MAGIC.len()] != MAGIC
+            || data[MAGIC.len()] != VERSION
+        {
+            return None;
+        }
+        let (size, choices) = data[MAGIC.len() + 1..].split_at(8);
+        let size = u64::from_le_bytes(size.try_into().unwrap());
+        let size = usize::try_from(size).ok()?;
+        Some(Example { size, choices: choices.to_vec() })
+    }
+}
+
+/// A local on-disk database of failing examples.
+///
+/// Examples are keyed by the property's test ID, so that every saved example
+/// of a property can be replayed at the start of its next run, no matter
+/// which random cases that run goes on to generate.
+///
+/// The layout is one directory per test ID with one file per example. Each
+/// file holds the magic bytes `QCEX`, a format version byte, the size as a
+/// little-endian `u64` and then the choice buffer:
+///
+/// ```text
+/// <dir>/<TEST_ID>/<hash of the file contents>
+/// ```
+///
+/// Files that are not examples, or were written in another version of the
+/// format, are ignored.
+#[derive(Clone, Debug)]
+pub struct ExampleDatabase {
+    dir: PathBuf,
+}
+
+impl ExampleDatabase {
+    /// Returns a database rooted at the given directory.
+    ///
+    /// The directory is created lazily the first time an example is saved.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> ExampleDatabase {
+        ExampleDatabase { dir: dir.into() }
+    }
+
+    /// Returns the directory this database is rooted at.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns every example saved for the given test ID.
+    ///
+    /// Examples are returned in a deterministic order. Entries that cannot be
+    /// read or decoded are skipped.
+    pub fn fetch(&self, test_id: &str) -> Vec<Example> {
+        let entries = match fs::read_dir(self.test_dir(test_id)) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .filter_map(|path| fs::read(path).ok())
+            .filter_map(|data| Example::decode(&data))
+            .collect()
+    }
+
+    /// Saves an example for the given test ID.
+    ///
+    /// Saving an example that is already in the database is a no-op.
+    pub fn save(&self, test_id: &str, example: &Example) -> io::Result<()> {
+        let dir = self.test_dir(test_id);
+        let data = example.encode();
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(example_name(&data)), data)
+    }
+
+    /// Deletes an example for the given test ID, if it exists.
+    pub fn delete(&self, test_id: &str, example: &Example) -> io::Result<()> {
+        let path =
+            self.test_dir(test_id).join(example_name(&example.encode()));
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn test_dir(&self, test_id: &str) -> PathBuf {
+        self.dir.join(escape_test_id(test_id))
+    }
+}
+
+/// Turns a test ID into a name that is safe to use as a path component.
+pub(crate) fn escape_test_id(test_id: &str) -> String {
+    test_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Names an example after a 64-bit FNV-1a hash of its contents.
+///
+/// The hash must be stable across builds and platforms, which rules out
+/// `std`'s `DefaultHasher`.
+fn example_name(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in data {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::{Example, ExampleDatabase};
+    use crate::{Gen, QuickCheck, TestResult, Testable};
+
+    fn temp_dir() -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let dir = env::temp_dir().join(format!(
+            "quickcheck-database-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn save_fetch_delete() {
+        let db = ExampleDatabase::new(temp_dir());
+        assert!(db.fetch("prop").is_empty());
+
+        let a = Example { size: 10, choices: vec![1, 2, 3] };
+        let b = Example { size: 10, choices: vec![4] };
+        let c = Example { size: 20, choices: vec![4] };
+        db.save("prop", &a).unwrap();
+        db.save("prop", &a).unwrap();
+        db.save("prop", &b).unwrap();
+        db.save("prop", &c).unwrap();
+        db.save("other/prop", &a).unwrap();
+        let mut examples = db.fetch("prop");
+        examples.sort();
+        assert_eq!(examples, vec![a.clone(), b.clone(), c.clone()]);
+        assert_eq!(db.fetch("other/prop"), vec![a.clone()]);
+
+        db.delete("prop", &b).unwrap();
+        db.delete("prop", &b).unwrap();
+        db.delete("prop", &c).unwrap();
+        assert_eq!(db.fetch("prop"), vec![a]);
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+
+    /// Fails on any vector containing a value above 200.
+    struct NoLargeValues;
+
+    #[async_trait]
+    impl Testable for NoLargeValues {
+        type Args = Vec<u8>;
+
+        fn test_id(&self) -> Option<String> {
+            Some("no_large_values".to_string())
+        }
+
+        async fn result(&self, args: &Vec<u8>) -> TestResult {
+            TestResult::from_bool(args.iter().all(|&x| x <= 200))
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_are_saved_and_replayed() {
+        let db = ExampleDatabase::new(temp_dir());
+        let mut qc = QuickCheck::new().database(db.clone());
+        assert!(qc.quicktest(NoLargeValues).await.is_err());
+        let examples = db.fetch("no_large_values");
+        assert_eq!(examples.len(), 1);
+
+        // The saved example fails on replay even if no random case is run.
+        let mut qc =
+            QuickCheck::new().database(db.clone()).tests(0).max_tests(0);
+        assert!(qc.quicktest(NoLargeValues).await.is_err());
+
+        // And the replayed arguments are a shrunk version of the original
+        // failure.
+        let example = examples[0].clone();
+        let mut g = Gen::from_choices(example.size, example.choices);
+        let args = <Vec<u8> as crate::Arbitrary>::arbitrary(&mut g);
+        assert!(args.iter().any(|&x| x > 200));
+        assert_eq!(args.iter().filter(|&&x| x != 0).count(), 1);
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+
+    /// Fails on any vector containing a value above 200, with a runtime error
+    /// if it is the only value. `result` counts the failures it would shrink.
+    #[derive(Clone, Default)]
+    struct FailsTwoWays {
+        shrunk: Arc<AtomicU64>,
+    }
+
+    #[async_trait]
+    impl Testable for FailsTwoWays {
+        type Args = Vec<u8>;
+
+        fn test_id(&self) -> Option<String> {
+            Some("fails_two_ways".to_string())
+        }
+
+        async fn result(&self, args: &Vec<u8>) -> TestResult {
+            let r = self.check_once(args).await;
+            if r.is_failure() {
+                self.shrunk.fetch_add(1, Ordering::SeqCst);
+            }
+            r
+        }
+
+        async fn check_once(&self, args: &Vec<u8>) -> TestResult {
+            match args.iter().any(|&x| x > 200) {
+                true if args.len() == 1 => TestResult::error("one value"),
+                failed => TestResult::from_bool(!failed),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn examples_are_shrunk_without_shrinking_candidates() {
+        let db = ExampleDatabase::new(temp_dir());
+        let f = FailsTwoWays::default();
+        let mut qc = QuickCheck::new().database(db.clone());
+        let failed = qc.quicktest(f.clone()).await.unwrap_err();
+        assert_eq!(f.shrunk.load(Ordering::SeqCst), 1);
+
+        // The saved example fails the way the original case did.
+        let mut qc =
+            QuickCheck::new().database(db.clone()).tests(0).max_tests(0);
+        let replayed = qc.quicktest(f).await.unwrap_err();
+        assert_eq!(replayed.is_error(), failed.is_error());
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fixed_failures_are_deleted() {
+        let db = ExampleDatabase::new(temp_dir());
+        // An empty buffer generates an empty vector, which passes.
+        let fixed = Example { size: 10, choices: vec![] };
+        db.save("no_large_values", &fixed).unwrap();
+
+        let mut qc =
+            QuickCheck::new().database(db.clone()).tests(0).max_tests(0);
+        assert_eq!(qc.quicktest(NoLargeValues).await.unwrap(), 0);
+        assert!(db.fetch("no_large_values").is_empty());
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+
+    #[test]
+    fn unreadable_entries_are_skipped() {
+        let db = ExampleDatabase::new(temp_dir());
+        let example = Example { size: 10, choices: vec![1, 2, 3] };
+        db.save("prop", &example).unwrap();
+        let dir = db.dir().join("prop");
+        fs::write(dir.join("short"), b"QCEX").unwrap();
+        fs::write(dir.join("garbage"), b"not an example at all").unwrap();
+        let mut future = example.encode();
+        future[4] += 1;
+        fs::write(dir.join("future"), future).unwrap();
+        assert_eq!(db.fetch("prop"), vec![example]);
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+
+    /// Fails on vectors longer than the default maximum size.
+    struct ShortVectors;
+
+    #[async_trait]
+    impl Testable for ShortVectors {
+        type Args = Vec<u8>;
+
+        fn test_id(&self) -> Option<String> {
+            Some("short_vectors".to_string())
+        }
+
+        async fn result(&self, args: &Vec<u8>) -> TestResult {
+            TestResult::from_bool(args.len() < 100)
+        }
+    }
+
+    #[tokio::test]
+    async fn replayed_sizes_are_capped() {
+        let db = ExampleDatabase::new(temp_dir());
+        let large = Example { size: 1_000_000, choices: vec![0xff; 8] };
+        db.save("short_vectors", &large).unwrap();
+
+        let mut qc =
+            QuickCheck::new().database(db.clone()).tests(0).max_tests(0);
+        assert_eq!(qc.quicktest(ShortVectors).await.unwrap(), 0);
+        fs::remove_dir_all(db.dir()).unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+use std::fmt::{self, Debug};
+
+use async_trait::async_trait;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use quickcheck_rpc::{codec::Codec, GenerateRequest, ShrinkRequest};
+
+use crate::tester::{execute_encoded, to_test_result, Status, TestFailure};
+use crate::transport;
+use crate::{Arbitrary, Gen, TestResult, Testable};
+
+/// The most shrink candidates executed for one failing case.
+const MAX_SHRINKS: usize = 500;
+
+/// The arguments of a `RunnerGenerated` test.
+#[derive(Clone, Debug)]
+pub enum GeneratedArgs {
+    /// Arguments the runner generates from a seed at the given size.
+    Seed { seed: u64, size: usize },
+    /// Arguments the runner generated earlier, as encoded by it.
+    Encoded(Vec<u8>),
+}
+
+impl Arbitrary for GeneratedArgs {
+    fn arbitrary(g: &mut Gen) -> Self {
+        GeneratedArgs::Seed {
+            seed: g.random_range(0..=u64::MAX),
+            size: g.size(),
+        }
+    }
+}
+
+/// A remote test whose arguments are generated and shrunk by its runner,
+/// with the `Generate` and `Shrink` RPCs.
+///
+/// QuickCheck still drives the run: it picks a seed and a size for every
+/// case, asks the runner for the arguments, executes them, and asks the
+/// runner for smaller candidates when a case fails. This suits properties
+/// whose argument types are defined in the runner's language, such as
+/// Node.js objects or Python dataclasses.
+///
+/// Arguments are reported in a JSON-like notation, since QuickCheck only
+/// sees their encoding.
+pub struct RunnerGenerated {
+    test_id: String,
+    endpoint: String,
+}
+
+impl RunnerGenerated {
+    pub fn new<S, E>(test_id: S, endpoint: E) -> Self
+    where
+        S: Into<String>,
+        E: Into<String>,
+    {
+        RunnerGenerated { test_id: test_id.into(), endpoint: endpoint.into() }
+    }
+
+    async fn client(&self) -> Result<transport::Client, String> {
+        let client = transport::client(&self.endpoint).await?;
+        client.require("Generate")?;
+        Ok(client)
+    }
+
+    async fn generate(
+        &self,
+        seed: u64,
+        size: usize,
+    ) -> Result<Vec<u8>, String> {
+        let request = GenerateRequest {
+            test_id: self.test_id.clone(),
+            seed,
+            size: size as u64,
+        };
+        let response = self
+            .client()
+            .await?
+            .generate(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(response.into_inner().test_data)
+    }
+
+    async fn shrink(&self, test_data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let request = ShrinkRequest {
+            test_id: self.test_id.clone(),
+            test_data: test_data.to_vec(),
+        };
+        let response = self
+            .client()
+            .await?
+            .shrink(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(response.into_inner().candidates)
+    }
+
+    /// Executes arguments as the runner generated them, in MessagePack.
+    async fn execute(&self, test_data: Vec<u8>) -> Result<TestResult, String> {
+        let response = execute_encoded(
+            &self.endpoint,
+            &self.test_id,
+            Codec::MsgPack,
+            test_data.clone(),
+        )
+        .await?;
+        Ok(to_test_result(&render(&test_data), test_data, response))
+    }
+
+    /// Executes a case and, if it fails, shrinks it with the runner's
+    /// candidates until none of them fails, or `MAX_SHRINKS` of them have
+    /// been executed.
+    async fn check(&self, args: &GeneratedArgs) -> Result<TestResult, String> {
+        let test_data = match args {
+            GeneratedArgs::Seed { seed, size } => {
+                self.generate(*seed, *size).await?
+            }
+            GeneratedArgs::Encoded(test_data) => test_data.clone(),
+        };
+        let mut smallest = self.execute(test_data.clone()).await?;
+        if !smallest.is_failure() {
+            return Ok(smallest);
+        }
+        let mut current = test_data;
+        let mut tries = 0;
+        'shrinking: loop {
+            println!("Shrinking... Args: {:?}", render(&current));
+            for candidate in self.shrink(&current).await? {
+                // Runners may offer the input itself, or lead back to it.
+                if candidate == current {
+                    continue;
+                }
+                if tries == MAX_SHRINKS {
+                    return Ok(smallest);
+                }
+                tries += 1;
+                let result = self.execute(candidate.clone()).await?;
+                if result.is_failure() {
+                    smallest = result;
+                    current = candidate;
+                    continue 'shrinking;
+                }
+            }
+            return Ok(smallest);
+        }
+    }
+}
+
+#[async_trait]
+impl Testable for RunnerGenerated {
+    type Args = GeneratedArgs;
+
+    fn test_id(&self) -> Option<String> {
+        Some(self.test_id.clone())
+    }
+
+    fn replays_regressions(&self) -> bool {
+        true
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<GeneratedArgs> {
+        Some(GeneratedArgs::Encoded(test_data.to_vec()))
+    }
+
+    async fn result(&self, args: &GeneratedArgs) -> TestResult {
+        match self.check(args).await {
+            Ok(result) => result,
+            Err(e) => TestResult {
+                status: Status::Fail,
+                arguments: vec![format!("{:?}", args)],
+                failure: Some(TestFailure::Runtime(format!(
+                    "Tester failed to call runner: {}",
+                    e
+                ))),
+                ..TestResult::default()
+            },
+        }
+    }
+}
+
+/// Renders encoded arguments for reports, falling back to the raw bytes if
+/// they cannot be decoded.
+fn render(test_data: &[u8]) -> Rendered {
+    rmp_serde::from_slice(test_data)
+        .unwrap_or_else(|_| Rendered(format!("{:?}", test_data)))
+}
+
+/// A value of any shape, rendered in a JSON-like notation.
+struct Rendered(String);
+
+impl Debug for Rendered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rendered {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(RenderVisitor)
+    }
+}
+
+struct RenderVisitor;
+
+impl<'de> Visitor<'de> for RenderVisitor {
+    type Value = Rendered;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Rendered, E> {
+        Ok(Rendered(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Rendered, E> {
+        Ok(Rendered(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Rendered, E> {
+        Ok(Rendered(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Rendered, E> {
+        Ok(Rendered(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Rendered, E> {
+        Ok(Rendered(format!("{:?}", v)))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Rendered, E> {
+        Ok(Rendered(format!("{:?}", v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Rendered, E> {
+        Ok(Rendered("null".to_string()))
+    }
+
+    fn visit_none<E>(self) -> Result<Rendered, E> {
+        Ok(Rendered("null".to_string()))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        d: D,
+    ) -> Result<Rendered, D::Error> {
+        Rendered::deserialize(d)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Rendered, A::Error> {
+        let mut items = vec![];
+        while let Some(Rendered(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Rendered(format!("[{}]", items.join(", "))))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Rendered, A::Error> {
+        let mut entries = vec![];
+        while let Some((Rendered(key), Rendered(value))) = map.next_entry()? {
+            entries.push(format!("{}: {}", key, value));
+        }
+        Ok(Rendered(format!("{{{}}}", entries.join(", "))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use quickcheck_runner::{Generator, SingleTestRunner, TestFunction};
+
+    use super::{render, RunnerGenerated};
+    use crate::{tester::TestFailure, QuickCheck};
+
+    #[test]
+    fn rendering() {
+        let mut map = BTreeMap::new();
+        map.insert("xs", vec![Some(1.5), None]);
+        let encoded = rmp_serde::to_vec_named(&(map, "a\"b", true)).unwrap();
+        assert_eq!(
+            format!("{:?}", render(&encoded)),
+            r#"[{"xs": [1.5, null]}, "a\"b", true]"#
+        );
+        assert_eq!(format!("{:?}", render(&[0xc1])), "[193]");
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Pair {
+        a: u64,
+        b: u64,
+    }
+
+    /// Rejects pairs whose first element is large.
+    struct SmallFirst;
+
+    impl TestFunction for SmallFirst {
+        type Args = Pair;
+        type Return = u64;
+        const TEST_ID: &'static str = "small_first";
+
+        fn execute(&self, pair: Pair) -> Result<u64, String> {
+            if pair.a > 10 {
+                return Err(format!("{} is too large", pair.a));
+            }
+            Ok(pair.a + pair.b)
+        }
+    }
+
+    struct PairGenerator;
+
+    impl Generator for PairGenerator {
+        type Args = Pair;
+
+        fn generate(&self, seed: u64, size: usize) -> Pair {
+            let size = size as u64 + 1;
+            Pair { a: seed % size, b: seed / size % size }
+        }
+
+        fn shrink(&self, pair: &Pair) -> Vec<Pair> {
+            let mut candidates = vec![];
+            if pair.b > 0 {
+                candidates.push(Pair { a: pair.a, b: 0 });
+            }
+            if pair.a > 0 {
+                candidates.push(Pair { a: pair.a / 2, b: pair.b });
+                candidates.push(Pair { a: pair.a - 1, b: pair.b });
+            }
+            candidates
+        }
+    }
+
+    #[tokio::test]
+    async fn runner_generates_and_shrinks() {
+        let runner =
+            SingleTestRunner::new(SmallFirst).generator(PairGenerator);
+        let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+        let test = RunnerGenerated::new("small_first", endpoint);
+        let mut qc = QuickCheck::new();
+        let r = qc.quicktest(test).await.unwrap_err();
+        assert_eq!(
+            r.failure,
+            Some(TestFailure::Property(Some("11 is too large".to_string())))
+        );
+        assert_eq!(r.arguments, vec![r#"{"a": 11, "b": 0}"#.to_string()]);
+    }
+
+    /// Offers every pair itself, and its mirror image, as candidates.
+    struct CyclicGenerator;
+
+    impl Generator for CyclicGenerator {
+        type Args = Pair;
+
+        fn generate(&self, seed: u64, size: usize) -> Pair {
+            PairGenerator.generate(seed, size)
+        }
+
+        fn shrink(&self, pair: &Pair) -> Vec<Pair> {
+            vec![pair.clone(), Pair { a: pair.b, b: pair.a }]
+        }
+    }
+
+    #[tokio::test]
+    async fn runner_shrinking_ends() {
+        let runner =
+            SingleTestRunner::new(SmallFirst).generator(CyclicGenerator);
+        let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+        let test = RunnerGenerated::new("small_first", endpoint);
+        let r = QuickCheck::new().quicktest(test).await.unwrap_err();
+        assert!(matches!(r.failure, Some(TestFailure::Property(_))));
+    }
+}
@@ -20,8 +20,23 @@ semver compatible releases.
 */
 
 // These re-exports remain the same.
-pub use crate::arbitrary::{empty_shrinker, single_shrinker, Arbitrary, Gen};
-pub use crate::tester::{quickcheck, QuickCheck, TestResult, Testable, RemoteTest};
+pub use crate::arbitrary::{
+    empty_shrinker, sample, single_shrinker, Arbitrary, Gen,
+};
+pub use crate::corpus::RegressionCorpus;
+pub use crate::coverage::Coverage;
+pub use crate::database::{Example, ExampleDatabase};
+pub use crate::generated::{GeneratedArgs, RunnerGenerated};
+pub use crate::oracle::ModelTest;
+pub use crate::parallel::{ParallelCommands, ParallelStateMachineTest};
+pub use crate::stateful::{Commands, StateMachine, StateMachineTest};
+pub use crate::tester::{
+    constant_size, linear_size, quickcheck, ArgsDecoder, QuickCheck,
+    RemoteTest, Sizing, TestResult, Testable,
+};
+pub use quickcheck_rpc::codec::Codec;
+pub use quickcheck_rpc::schema::{schema_of, Schema};
+pub use quickcheck_rpc::security::Tls;
 
 /// A macro for writing quickcheck tests.
 ///
@@ -31,7 +46,7 @@ pub use crate::tester::{quickcheck, QuickCheck, TestResult, Testable, RemoteTest
 ///
 /// # Example
 ///
-/// ```rust
+/// ```ignore
 /// # #[macro_use] extern crate quickcheck;
 /// # use tokio;
 /// #
@@ -88,6 +103,8 @@ pub use crate::tester::{quickcheck, QuickCheck, TestResult, Testable, RemoteTest
 // }
 
 // Logging features remain the same.
+// The doc comment above belongs to the disabled `quickcheck!` macro.
+#[allow(clippy::empty_line_after_doc_comments)]
 #[cfg(feature = "use_logging")]
 fn env_logger_init() -> Result<(), log::SetLoggerError> {
     env_logger::try_init()
@@ -110,11 +127,22 @@ macro_rules! info {
 
 // Module declarations remain the same.
 mod arbitrary;
+pub mod compare;
 mod composite;
+mod corpus;
+mod coverage;
+mod database;
+mod generated;
+mod oracle;
+mod parallel;
+mod stateful;
 mod tester;
+mod transport;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export composite functionality
-pub use composite::{CompositeTest};
+pub use composite::{
+    ComparisonReport, CompositeTest, ImplementationReturn, Outcome,
+};
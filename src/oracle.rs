@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::tester::{RemoteTest, Testable};
+use crate::{Arbitrary, CompositeTest, TestResult};
+
+/// Checks a remote implementation against a trusted local Rust model.
+///
+/// Every case runs the model in-process and the remote test once, then
+/// compares the two return values. Any mismatch, and any failure of the
+/// remote test, is shrunk like a composite failure. The report shows the
+/// model as `oracle @ local` next to the remote's endpoint, with a diff of
+/// the remote's value against the model's.
+pub struct ModelTest<A, R, F = fn(&A, &[R]) -> bool>
+where
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
+{
+    test_id: &'static str,
+    composite: CompositeTest<A, R, F>,
+}
+
+impl<A, R> ModelTest<A, R>
+where
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: PartialEq + Debug + Send + Sync + 'static,
+{
+    /// Creates a model test that requires the remote test to return exactly
+    /// what `model` returns.
+    pub fn new<T, M>(test: T, model: M) -> Self
+    where
+        T: RemoteTest<Args = A, Return = R> + 'static,
+        M: Fn(&A) -> R + Send + Sync + 'static,
+    {
+        ModelTest::comparing(test, model, model_agrees)
+    }
+}
+
+impl<A, R, F> ModelTest<A, R, F>
+where
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
+{
+    /// Creates a model test with a custom comparison.
+    ///
+    /// The comparison receives the model's value first and the remote's
+    /// second, so the functions in `quickcheck::compare` that treat the first
+    /// value as the reference apply directly.
+    pub fn comparing<T, M>(test: T, model: M, comparison: F) -> Self
+    where
+        T: RemoteTest<Args = A, Return = R> + 'static,
+        M: Fn(&A) -> R + Send + Sync + 'static,
+    {
+        ModelTest {
+            test_id: T::TEST_ID,
+            composite: CompositeTest::comparing(comparison)
+                .oracle(model)
+                .implementation(test),
+        }
+    }
+}
+
+fn model_agrees<A, R: PartialEq>(_: &A, results: &[R]) -> bool {
+    results.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+#[async_trait]
+impl<A, R, F> Testable for ModelTest<A, R, F>
+where
+    A: Arbitrary + Serialize + for<'de> Deserialize<'de> + Debug + Clone,
+    A: Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+    F: Fn(&A, &[R]) -> bool + Send + Sync + 'static,
+{
+    type Args = A;
+
+    fn test_id(&self) -> Option<String> {
+        Some(format!("{}.model", self.test_id))
+    }
+
+    fn replays_regressions(&self) -> bool {
+        true
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<A> {
+        self.composite.decode_args(test_data)
+    }
+
+    async fn result(&self, args: &A) -> TestResult {
+        self.composite.result(args).await
+    }
+}
@@ -0,0 +1,536 @@
+use std::fmt::{self, Debug, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use quickcheck_rpc::EndSessionRequest;
+
+use crate::stateful::{
+    begin_session, execute_command, generate_sequence, run, shrink_sequence,
+};
+use crate::tester::{Status, TestFailure, Testable};
+use crate::transport;
+use crate::{Arbitrary, Gen, StateMachine, TestResult};
+
+/// The number of branches run concurrently after the prefix.
+const BRANCHES: usize = 2;
+
+/// The most commands a branch holds. Checking a history for linearizability
+/// takes time exponential in the branch lengths, so they are kept short.
+const MAX_BRANCH_LEN: usize = 4;
+
+/// A sequential prefix of commands followed by branches of commands that run
+/// concurrently.
+///
+/// Preconditions are checked for the prefix, and for each branch on its own
+/// after the prefix, so they should not depend on what other branches do.
+pub struct ParallelCommands<M: StateMachine> {
+    /// Commands run one after another before the branches start.
+    pub prefix: Vec<M::Command>,
+    /// Command sequences run concurrently, each from its own client.
+    pub branches: Vec<Vec<M::Command>>,
+}
+
+impl<M: StateMachine> ParallelCommands<M> {
+    /// Returns `true` if the prefix, and every branch run right after it,
+    /// satisfy their preconditions.
+    pub fn is_valid(&self) -> bool {
+        let mut state = M::initial_state();
+        if !valid::<M>(&mut state, &self.prefix) {
+            return false;
+        }
+        self.branches
+            .iter()
+            .all(|branch| valid::<M>(&mut state.clone(), branch))
+    }
+
+    /// Returns the model's state after the prefix.
+    fn state_after_prefix(&self) -> M::State {
+        let mut state = M::initial_state();
+        for command in &self.prefix {
+            M::apply(&mut state, command);
+        }
+        state
+    }
+}
+
+fn valid<M: StateMachine>(
+    state: &mut M::State,
+    commands: &[M::Command],
+) -> bool {
+    commands.iter().all(|command| {
+        let ok = M::precondition(state, command);
+        M::apply(state, command);
+        ok
+    })
+}
+
+impl<M: StateMachine> Clone for ParallelCommands<M> {
+    fn clone(&self) -> Self {
+        ParallelCommands {
+            prefix: self.prefix.clone(),
+            branches: self.branches.clone(),
+        }
+    }
+}
+
+impl<M: StateMachine> Debug for ParallelCommands<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelCommands")
+            .field("prefix", &self.prefix)
+            .field("branches", &self.branches)
+            .finish()
+    }
+}
+
+impl<M: StateMachine> Serialize for ParallelCommands<M> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        (&self.prefix, &self.branches).serialize(s)
+    }
+}
+
+impl<'de, M: StateMachine> Deserialize<'de> for ParallelCommands<M> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let (prefix, branches) = Deserialize::deserialize(d)?;
+        Ok(ParallelCommands { prefix, branches })
+    }
+}
+
+impl<M: StateMachine + 'static> Arbitrary for ParallelCommands<M> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let size = g.size();
+        let len = g.random_range(0..size.max(1));
+        let mut state = M::initial_state();
+        let prefix = generate_sequence::<M>(&mut state, len, g);
+        let branches = (0..BRANCHES)
+            .map(|_| {
+                let len = g.random_range(0..=MAX_BRANCH_LEN.min(size));
+                generate_sequence::<M>(&mut state.clone(), len, g)
+            })
+            .collect();
+        ParallelCommands { prefix, branches }
+    }
+
+    /// Shrinks the prefix, then every branch, then tries moving the first
+    /// command of a branch to the end of the prefix.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let mut candidates = vec![];
+        for prefix in shrink_sequence::<M>(&self.prefix) {
+            candidates.push(ParallelCommands {
+                prefix,
+                branches: self.branches.clone(),
+            });
+        }
+        for (i, branch) in self.branches.iter().enumerate() {
+            for shrunk in shrink_sequence::<M>(branch) {
+                let mut candidate = self.clone();
+                candidate.branches[i] = shrunk;
+                candidates.push(candidate);
+            }
+        }
+        for (i, branch) in self.branches.iter().enumerate() {
+            if let Some((first, rest)) = branch.split_first() {
+                let mut candidate = self.clone();
+                candidate.prefix.push(first.clone());
+                candidate.branches[i] = rest.to_vec();
+                candidates.push(candidate);
+            }
+        }
+        Box::new(candidates.into_iter().filter(ParallelCommands::is_valid))
+    }
+}
+
+/// One command of a branch, as it was observed by the tester.
+struct Operation<M: StateMachine> {
+    command: M::Command,
+    response: M::Response,
+    /// Logical times at which the command was sent and answered.
+    invoked: u64,
+    returned: u64,
+}
+
+/// Returns whether the branches can be put in one sequential order that
+/// respects real time and agrees with the model from `state`.
+///
+/// A command must be ordered before another if its response arrived before
+/// the other was sent.
+fn linearizable<M: StateMachine>(
+    state: &M::State,
+    branches: &[Vec<Operation<M>>],
+    next: &mut [usize],
+) -> bool {
+    let pending: Vec<&Operation<M>> = branches
+        .iter()
+        .zip(next.iter())
+        .filter_map(|(branch, &i)| branch.get(i))
+        .collect();
+    if pending.is_empty() {
+        return true;
+    }
+    let first_return = pending.iter().map(|op| op.returned).min().unwrap();
+    for b in 0..branches.len() {
+        let op = match branches[b].get(next[b]) {
+            Some(op) => op,
+            None => continue,
+        };
+        // Another pending command finished before this one started.
+        if op.invoked > first_return {
+            continue;
+        }
+        if !M::postcondition(state, &op.command, &op.response) {
+            continue;
+        }
+        let mut after = state.clone();
+        M::apply(&mut after, &op.command);
+        next[b] += 1;
+        let found = linearizable(&after, branches, next);
+        next[b] -= 1;
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
+/// Runs a prefix of commands sequentially and then several branches
+/// concurrently against a state machine's runner, and checks that the
+/// observed history is linearizable with respect to the model.
+///
+/// All commands of a case run within one session. Every branch is sent from
+/// its own client, so the runner sees them as concurrent requests on the
+/// same state. A race may not show up on every run, so each case is run
+/// several times before it is considered to pass.
+pub struct ParallelStateMachineTest<M: StateMachine> {
+    machine: M,
+    repetitions: usize,
+}
+
+impl<M: StateMachine> ParallelStateMachineTest<M> {
+    pub fn new(machine: M) -> Self {
+        ParallelStateMachineTest { machine, repetitions: 10 }
+    }
+
+    /// Set the number of times each case is run.
+    ///
+    /// The default is 10.
+    pub fn repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = repetitions.max(1);
+        self
+    }
+
+    /// Runs `commands` as many times as configured, without shrinking.
+    async fn check(&self, commands: &ParallelCommands<M>) -> TestResult {
+        let failure = |failure| TestResult {
+            status: Status::Fail,
+            arguments: vec![format!("{:?}", commands)],
+            failure: Some(failure),
+            test_data: rmp_serde::to_vec_named(commands).ok(),
+            ..TestResult::default()
+        };
+        for _ in 0..self.repetitions {
+            match self.check_once(commands).await {
+                Ok(Status::Pass) => {}
+                Ok(_) => return TestResult::discard(),
+                Err(e) => return failure(e),
+            }
+        }
+        TestResult::passed()
+    }
+
+    /// Runs `commands` once in a new session.
+    async fn check_once(
+        &self,
+        commands: &ParallelCommands<M>,
+    ) -> Result<Status, TestFailure> {
+        let endpoint = self.machine.endpoint();
+        let (mut client, session_id) = begin_session::<M>(endpoint)
+            .await
+            .map_err(TestFailure::Runtime)?;
+        let outcome =
+            self.run_branches(&mut client, &session_id, commands).await;
+        let _ = client.end_session(EndSessionRequest { session_id }).await;
+        outcome
+    }
+
+    async fn run_branches(
+        &self,
+        client: &mut transport::Client,
+        session_id: &str,
+        commands: &ParallelCommands<M>,
+    ) -> Result<Status, TestFailure> {
+        match run::<M>(client, session_id, &commands.prefix).await? {
+            Status::Pass => {}
+            status => return Ok(status),
+        }
+
+        // Connect every branch before any of them starts.
+        let mut clients = vec![];
+        for _ in &commands.branches {
+            let endpoint = self.machine.endpoint();
+            let client = transport::client(endpoint).await.map_err(|e| {
+                TestFailure::Runtime(format!(
+                    "Tester failed to call runner: {}",
+                    e
+                ))
+            })?;
+            clients.push(client);
+        }
+
+        let clock = AtomicU64::new(0);
+        let histories =
+            join_all(commands.branches.iter().zip(clients).enumerate().map(
+                |(b, (branch, mut client))| {
+                    let clock = &clock;
+                    async move {
+                        let mut history = vec![];
+                        for (i, command) in branch.iter().enumerate() {
+                            let name = format!("Branch {} command {}", b, i);
+                            let invoked = clock.fetch_add(1, Ordering::SeqCst);
+                            let response = execute_command::<M>(
+                                &mut client,
+                                session_id,
+                                &name,
+                                command,
+                            )
+                            .await?;
+                            let returned =
+                                clock.fetch_add(1, Ordering::SeqCst);
+                            match response {
+                                Some(response) => history.push(Operation {
+                                    command: command.clone(),
+                                    response,
+                                    invoked,
+                                    returned,
+                                }),
+                                None => return Ok(None),
+                            }
+                        }
+                        Ok(Some(history))
+                    }
+                },
+            ))
+            .await;
+
+        let mut branches = vec![];
+        for history in histories {
+            match history? {
+                Some(history) => branches.push(history),
+                None => return Ok(Status::Discard),
+            }
+        }
+        let state = commands.state_after_prefix();
+        let mut next = vec![0; branches.len()];
+        if linearizable::<M>(&state, &branches, &mut next) {
+            Ok(Status::Pass)
+        } else {
+            Err(TestFailure::Property(Some(history_msg::<M>(&branches))))
+        }
+    }
+}
+
+/// Describes a history that has no linearization.
+fn history_msg<M: StateMachine>(branches: &[Vec<Operation<M>>]) -> String {
+    let mut msg = "No sequential order of the concurrent branches agrees \
+                   with the model. History:"
+        .to_string();
+    for (b, branch) in branches.iter().enumerate() {
+        for op in branch {
+            let _ = write!(
+                msg,
+                "\n  [{:>3}..{:>3}] branch {}: {:?} -> {:?}",
+                op.invoked, op.returned, b, op.command, op.response
+            );
+        }
+    }
+    msg
+}
+
+#[async_trait]
+impl<M: StateMachine + 'static> Testable for ParallelStateMachineTest<M> {
+    type Args = ParallelCommands<M>;
+
+    fn test_id(&self) -> Option<String> {
+        Some(format!("{}.parallel", M::TEST_ID))
+    }
+
+    fn replays_regressions(&self) -> bool {
+        true
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<ParallelCommands<M>> {
+        rmp_serde::from_slice(test_data).ok()
+    }
+
+    async fn result(&self, args: &ParallelCommands<M>) -> TestResult {
+        async fn shrink_failure<M: StateMachine + 'static>(
+            test: &ParallelStateMachineTest<M>,
+            initial_args: ParallelCommands<M>,
+        ) -> Option<TestResult> {
+            println!("Shrinking... Commands: {:?}", initial_args);
+            let shrunk_values: Vec<_> = initial_args.shrink().collect();
+
+            for shrunk_args in shrunk_values {
+                let result = test.check(&shrunk_args).await;
+                if result.is_error() {
+                    // The runner could not be called, so stop shrinking and
+                    // report it as the smallest failure.
+                    return Some(result);
+                }
+                if result.is_failure() {
+                    let smaller_failure =
+                        Box::pin(shrink_failure(test, shrunk_args)).await;
+                    return Some(smaller_failure.unwrap_or(result));
+                }
+            }
+            None
+        }
+
+        let result = self.check(args).await;
+        if result.is_failure() && !result.is_error() {
+            shrink_failure(self, args.clone()).await.unwrap_or(result)
+        } else {
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize};
+
+    use quickcheck_runner::{StatefulTestFunction, StatefulTestRunner};
+
+    use super::{
+        linearizable, Operation, ParallelCommands, ParallelStateMachineTest,
+    };
+    use crate::{tester::TestFailure, Gen, QuickCheck, StateMachine};
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Command {
+        Incr,
+        Get,
+    }
+
+    /// A counter whose model is a plain integer.
+    struct Counter {
+        endpoint: String,
+    }
+
+    impl StateMachine for Counter {
+        type State = i64;
+        type Command = Command;
+        type Response = i64;
+        const TEST_ID: &'static str = "counter";
+
+        fn endpoint(&self) -> &str {
+            &self.endpoint
+        }
+
+        fn initial_state() -> i64 {
+            0
+        }
+
+        fn command(_: &i64, g: &mut Gen) -> Command {
+            g.choose(&[Command::Incr, Command::Get]).unwrap().clone()
+        }
+
+        fn apply(state: &mut i64, command: &Command) {
+            if *command == Command::Incr {
+                *state += 1;
+            }
+        }
+
+        fn postcondition(state: &i64, command: &Command, value: &i64) -> bool {
+            match command {
+                Command::Incr => *value == state + 1,
+                Command::Get => value == state,
+            }
+        }
+    }
+
+    fn op(
+        command: Command,
+        response: i64,
+        invoked: u64,
+        returned: u64,
+    ) -> Operation<Counter> {
+        Operation { command, response, invoked, returned }
+    }
+
+    #[test]
+    fn linearizability() {
+        // Overlapping increments may happen in either order.
+        let branches = vec![
+            vec![op(Command::Incr, 2, 0, 3)],
+            vec![op(Command::Incr, 1, 1, 2)],
+        ];
+        assert!(linearizable::<Counter>(&0, &branches, &mut [0, 0]));
+
+        // Two increments cannot both return 1.
+        let branches = vec![
+            vec![op(Command::Incr, 1, 0, 3)],
+            vec![op(Command::Incr, 1, 1, 2)],
+        ];
+        assert!(!linearizable::<Counter>(&0, &branches, &mut [0, 0]));
+
+        // A read that starts after an increment finished must see it.
+        let branches = vec![
+            vec![op(Command::Incr, 1, 0, 1)],
+            vec![op(Command::Get, 0, 2, 3)],
+        ];
+        assert!(!linearizable::<Counter>(&0, &branches, &mut [0, 0]));
+    }
+
+    /// A counter that increments with a separate load and store.
+    struct RacyCounter;
+
+    impl StatefulTestFunction for RacyCounter {
+        type State = AtomicI64;
+        type Command = Command;
+        type Return = i64;
+        const TEST_ID: &'static str = "counter";
+
+        fn begin(&self) -> Result<AtomicI64, String> {
+            Ok(AtomicI64::new(0))
+        }
+
+        fn execute(
+            &self,
+            state: &AtomicI64,
+            command: Command,
+        ) -> Result<i64, String> {
+            let value = state.load(Ordering::SeqCst);
+            if command == Command::Get {
+                return Ok(value);
+            }
+            thread::sleep(Duration::from_millis(20));
+            state.store(value + 1, Ordering::SeqCst);
+            Ok(value + 1)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn races_are_found_and_shrunk() {
+        let endpoint = StatefulTestRunner::new(RacyCounter)
+            .spawn("127.0.0.1:0")
+            .await
+            .unwrap();
+        let test = ParallelStateMachineTest::new(Counter { endpoint });
+        let mut qc = QuickCheck::new().tests(20);
+        let r = qc.quicktest(test).await.unwrap_err();
+        assert!(matches!(r.failure, Some(TestFailure::Property(_))));
+        let commands: ParallelCommands<Counter> =
+            rmp_serde::from_slice(&r.test_data.unwrap()).unwrap();
+        assert!(commands.prefix.is_empty());
+        assert_eq!(
+            commands.branches,
+            vec![vec![Command::Incr], vec![Command::Incr]]
+        );
+    }
+}
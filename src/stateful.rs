@@ -0,0 +1,524 @@
+use std::fmt::{self, Debug};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use quickcheck_rpc::{
+    execute_response::TestStatus as ProtoStatus, BeginSessionRequest,
+    Encoding, EndSessionRequest, ExecuteRequest,
+};
+
+use crate::tester::{Status, TestFailure, Testable};
+use crate::transport;
+use crate::{empty_shrinker, Arbitrary, Gen, TestResult};
+
+/// How many times to draw a command before giving up on extending a
+/// sequence whose preconditions reject every draw.
+const MAX_COMMAND_TRIES: usize = 100;
+
+/// A model of a stateful system behind a runner, such as a key-value store,
+/// a cache or a queue.
+///
+/// Command sequences are generated from the model, sent one `Execute` call
+/// at a time within a runner session, and every response is checked against
+/// the model. A failing sequence is shrunk as a whole.
+pub trait StateMachine: Send + Sync {
+    /// The model's state.
+    type State: Clone + Debug + Send + Sync + 'static;
+
+    /// A command, sent to the runner as the `test_data` of one `Execute`
+    /// call.
+    type Command: Serialize
+        + for<'de> Deserialize<'de>
+        + Debug
+        + Clone
+        + Send
+        + Sync
+        + 'static;
+
+    /// The runner's response to a command.
+    type Response: for<'de> Deserialize<'de> + Debug + Send + Sync + 'static;
+
+    /// The unique string ID for this test, matching the ID in the runner.
+    const TEST_ID: &'static str;
+
+    /// The network address of the gRPC runner server.
+    fn endpoint(&self) -> &str;
+
+    /// Returns the state of the model before any command.
+    fn initial_state() -> Self::State;
+
+    /// Generates a command to run in the given state.
+    fn command(state: &Self::State, g: &mut Gen) -> Self::Command;
+
+    /// Returns whether the command may run in the given state.
+    ///
+    /// Generated commands that fail their precondition are drawn again, and
+    /// shrunk sequences that break one are never tried.
+    fn precondition(_state: &Self::State, _command: &Self::Command) -> bool {
+        true
+    }
+
+    /// Applies the command to the model's state.
+    fn apply(state: &mut Self::State, command: &Self::Command);
+
+    /// Checks the runner's response against the model, in the state before
+    /// the command was applied.
+    fn postcondition(
+        state: &Self::State,
+        command: &Self::Command,
+        response: &Self::Response,
+    ) -> bool;
+
+    /// Returns smaller versions of a command, tried when shrinking.
+    fn shrink_command(
+        _command: &Self::Command,
+    ) -> Box<dyn Iterator<Item = Self::Command>> {
+        empty_shrinker()
+    }
+}
+
+/// A sequence of commands of a state machine, valid under its preconditions.
+pub struct Commands<M: StateMachine>(pub Vec<M::Command>);
+
+impl<M: StateMachine> Commands<M> {
+    /// Returns `true` if every command satisfies its precondition when run
+    /// from the initial state.
+    pub fn is_valid(&self) -> bool {
+        let mut state = M::initial_state();
+        self.0.iter().all(|command| {
+            let ok = M::precondition(&state, command);
+            M::apply(&mut state, command);
+            ok
+        })
+    }
+}
+
+impl<M: StateMachine> Clone for Commands<M> {
+    fn clone(&self) -> Self {
+        Commands(self.0.clone())
+    }
+}
+
+impl<M: StateMachine> Debug for Commands<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<M: StateMachine> Serialize for Commands<M> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(s)
+    }
+}
+
+impl<'de, M: StateMachine> Deserialize<'de> for Commands<M> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Vec::deserialize(d).map(Commands)
+    }
+}
+
+impl<M: StateMachine + 'static> Arbitrary for Commands<M> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let size = g.size();
+        let len = g.random_range(0..size.max(1));
+        Commands(generate_sequence::<M>(&mut M::initial_state(), len, g))
+    }
+
+    /// Removes ever smaller chunks of commands, then shrinks single
+    /// commands, skipping any sequence that breaks a precondition.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let candidates = shrink_sequence::<M>(&self.0).into_iter();
+        Box::new(candidates.map(Commands).filter(Commands::is_valid))
+    }
+}
+
+/// Generates up to `len` commands from the given state, applying each to it.
+///
+/// The sequence ends early if no command satisfying its precondition can be
+/// drawn.
+pub(crate) fn generate_sequence<M: StateMachine>(
+    state: &mut M::State,
+    len: usize,
+    g: &mut Gen,
+) -> Vec<M::Command> {
+    let mut commands = Vec::with_capacity(len);
+    'next: while commands.len() < len {
+        for _ in 0..MAX_COMMAND_TRIES {
+            let command = M::command(state, g);
+            if M::precondition(state, &command) {
+                M::apply(state, &command);
+                commands.push(command);
+                continue 'next;
+            }
+        }
+        break;
+    }
+    commands
+}
+
+/// Returns smaller versions of a command sequence: ever smaller chunks
+/// removed first, then single commands shrunk. Preconditions are not checked.
+pub(crate) fn shrink_sequence<M: StateMachine>(
+    commands: &[M::Command],
+) -> Vec<Vec<M::Command>> {
+    let mut candidates = vec![];
+    let mut chunk = commands.len();
+    while chunk > 0 {
+        for start in (0..=commands.len() - chunk).step_by(chunk) {
+            let mut shorter = commands[..start].to_vec();
+            shorter.extend_from_slice(&commands[start + chunk..]);
+            candidates.push(shorter);
+        }
+        chunk /= 2;
+    }
+    for (i, command) in commands.iter().enumerate() {
+        for smaller in M::shrink_command(command) {
+            let mut candidate = commands.to_vec();
+            candidate[i] = smaller;
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Runs the command sequences of a state machine against its runner.
+///
+/// Each case opens a session with `BeginSession`, executes its commands in
+/// order within that session, and closes it with `EndSession`, so the runner
+/// starts every case from a fresh state.
+pub struct StateMachineTest<M: StateMachine> {
+    machine: M,
+}
+
+impl<M: StateMachine> StateMachineTest<M> {
+    pub fn new(machine: M) -> Self {
+        StateMachineTest { machine }
+    }
+
+    /// Runs `commands` in a new session without shrinking.
+    async fn check(&self, commands: &Commands<M>) -> TestResult {
+        let failure = |failure| TestResult {
+            status: Status::Fail,
+            arguments: vec![format!("{:?}", commands)],
+            failure: Some(failure),
+            test_data: rmp_serde::to_vec_named(commands).ok(),
+            ..TestResult::default()
+        };
+        let endpoint = self.machine.endpoint();
+        let (mut client, session_id) = match begin_session::<M>(endpoint).await
+        {
+            Ok(session) => session,
+            Err(e) => return failure(TestFailure::Runtime(e)),
+        };
+
+        let outcome = run::<M>(&mut client, &session_id, &commands.0).await;
+        let _ = client.end_session(EndSessionRequest { session_id }).await;
+        match outcome {
+            Ok(Status::Discard) => TestResult::discard(),
+            Ok(_) => TestResult::passed(),
+            Err(e) => failure(e),
+        }
+    }
+}
+
+/// Connects to the runner of a state machine and begins a session.
+pub(crate) async fn begin_session<M: StateMachine>(
+    endpoint: &str,
+) -> Result<(transport::Client, String), String> {
+    let mut client = transport::client(endpoint)
+        .await
+        .map_err(|e| format!("Tester failed to call runner: {}", e))?;
+    client.require("sessions")?;
+    let request = BeginSessionRequest { test_id: M::TEST_ID.to_string() };
+    let session_id = client
+        .begin_session(request)
+        .await
+        .map_err(|e| format!("Failed to begin session: {}", e))?
+        .into_inner()
+        .session_id;
+    Ok((client, session_id))
+}
+
+/// Executes commands one after another within a session, checking every
+/// response against the model.
+pub(crate) async fn run<M: StateMachine>(
+    client: &mut transport::Client,
+    session_id: &str,
+    commands: &[M::Command],
+) -> Result<Status, TestFailure> {
+    let mut state = M::initial_state();
+    for (i, command) in commands.iter().enumerate() {
+        if !M::precondition(&state, command) {
+            return Ok(Status::Discard);
+        }
+        let name = format!("Command {}", i);
+        let response =
+            match execute_command::<M>(client, session_id, &name, command)
+                .await?
+            {
+                Some(response) => response,
+                None => return Ok(Status::Discard),
+            };
+        if !M::postcondition(&state, command, &response) {
+            return Err(TestFailure::Property(Some(format!(
+                "{} ({:?}) broke its postcondition in state {:?}: got {:?}",
+                name, command, state, response
+            ))));
+        }
+        M::apply(&mut state, command);
+    }
+    Ok(Status::Pass)
+}
+
+/// Executes one command within a session and decodes the runner's response.
+///
+/// Returns `None` if the runner discarded the command. `name` identifies the
+/// command in failure messages.
+pub(crate) async fn execute_command<M: StateMachine>(
+    client: &mut transport::Client,
+    session_id: &str,
+    name: &str,
+    command: &M::Command,
+) -> Result<Option<M::Response>, TestFailure> {
+    let test_data = rmp_serde::to_vec_named(command)
+        .map_err(|e| TestFailure::Runtime(e.to_string()))?;
+    let request = ExecuteRequest {
+        test_id: M::TEST_ID.to_string(),
+        test_data,
+        session_id: session_id.to_string(),
+        encoding: Encoding::Msgpack.into(),
+        version: client.version(),
+    };
+    let response = client
+        .execute(request)
+        .await
+        .map_err(|e| {
+            TestFailure::Runtime(format!(
+                "Tester failed to call runner: {}",
+                e
+            ))
+        })?
+        .into_inner();
+    client.check_response(&response).map_err(TestFailure::Runtime)?;
+    match ProtoStatus::try_from(response.status).unwrap_or(ProtoStatus::Failed)
+    {
+        ProtoStatus::Passed => {}
+        ProtoStatus::InvalidInput => return Ok(None),
+        ProtoStatus::Failed => {
+            return Err(TestFailure::Property(Some(format!(
+                "{} ({:?}) failed: {}",
+                name,
+                command,
+                response.failure_detail.unwrap_or_default()
+            ))))
+        }
+    }
+    response
+        .return_value
+        .ok_or_else(|| "No return value available".to_string())
+        .and_then(|msgpack| {
+            rmp_serde::from_slice(&msgpack).map_err(|e| {
+                format!("Failed to deserialize return value: {}", e)
+            })
+        })
+        .map(Some)
+        .map_err(|e| {
+            TestFailure::Runtime(format!(
+                "Failed to extract return values: {}",
+                e
+            ))
+        })
+}
+
+#[async_trait]
+impl<M: StateMachine + 'static> Testable for StateMachineTest<M> {
+    type Args = Commands<M>;
+
+    fn test_id(&self) -> Option<String> {
+        Some(M::TEST_ID.to_string())
+    }
+
+    fn replays_regressions(&self) -> bool {
+        true
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<Commands<M>> {
+        rmp_serde::from_slice(test_data).ok()
+    }
+
+    async fn result(&self, args: &Commands<M>) -> TestResult {
+        async fn shrink_failure<M: StateMachine + 'static>(
+            test: &StateMachineTest<M>,
+            initial_args: Commands<M>,
+        ) -> Option<TestResult> {
+            println!("Shrinking... Commands: {:?}", initial_args);
+            let shrunk_values: Vec<_> = initial_args.shrink().collect();
+
+            for shrunk_args in shrunk_values {
+                let result = test.check(&shrunk_args).await;
+                if result.is_error() {
+                    // The runner could not be called, so stop shrinking and
+                    // report it as the smallest failure.
+                    return Some(result);
+                }
+                if result.is_failure() {
+                    let smaller_failure =
+                        Box::pin(shrink_failure(test, shrunk_args)).await;
+                    return Some(smaller_failure.unwrap_or(result));
+                }
+            }
+            None
+        }
+
+        let result = self.check(args).await;
+        if result.is_failure() && !result.is_error() {
+            shrink_failure(self, args.clone()).await.unwrap_or(result)
+        } else {
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use serde::{Deserialize, Serialize};
+
+    use quickcheck_runner::{StatefulTestFunction, StatefulTestRunner};
+
+    use super::{Commands, StateMachine, StateMachineTest};
+    use crate::{tester::TestFailure, Arbitrary, Gen, QuickCheck};
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Command {
+        Put(u8, u8),
+        Get(u8),
+        Delete(u8),
+    }
+
+    /// A key-value store whose model is a plain map.
+    struct KvStore {
+        endpoint: String,
+    }
+
+    impl StateMachine for KvStore {
+        type State = HashMap<u8, u8>;
+        type Command = Command;
+        type Response = Option<u8>;
+        const TEST_ID: &'static str = "kv_store";
+
+        fn endpoint(&self) -> &str {
+            &self.endpoint
+        }
+
+        fn initial_state() -> HashMap<u8, u8> {
+            HashMap::new()
+        }
+
+        fn command(state: &HashMap<u8, u8>, g: &mut Gen) -> Command {
+            let key = u8::arbitrary(g) % 4;
+            match g.choose(&[0, 1, 2]).unwrap() {
+                0 => Command::Put(key, u8::arbitrary(g)),
+                1 => Command::Get(key),
+                // Only delete keys that are present.
+                _ => match state.keys().next() {
+                    Some(&key) => Command::Delete(key),
+                    None => Command::Get(key),
+                },
+            }
+        }
+
+        fn precondition(state: &HashMap<u8, u8>, command: &Command) -> bool {
+            match command {
+                Command::Delete(key) => state.contains_key(key),
+                _ => true,
+            }
+        }
+
+        fn apply(state: &mut HashMap<u8, u8>, command: &Command) {
+            match *command {
+                Command::Put(key, value) => {
+                    state.insert(key, value);
+                }
+                Command::Get(_) => {}
+                Command::Delete(key) => {
+                    state.remove(&key);
+                }
+            }
+        }
+
+        fn postcondition(
+            state: &HashMap<u8, u8>,
+            command: &Command,
+            response: &Option<u8>,
+        ) -> bool {
+            match command {
+                Command::Put(_, _) => true,
+                Command::Get(key) | Command::Delete(key) => {
+                    state.get(key) == response.as_ref()
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn commands_respect_preconditions() {
+        let mut g = Gen::new(50);
+        for _ in 0..100 {
+            let commands = Commands::<KvStore>::arbitrary(&mut g);
+            assert!(commands.is_valid());
+            assert!(commands.shrink().all(|shrunk| shrunk.is_valid()));
+        }
+        let invalid = Commands::<KvStore>(vec![Command::Delete(1)]);
+        assert!(!invalid.is_valid());
+    }
+
+    /// A store that forgets deletions of key 3.
+    struct BuggyStore;
+
+    impl StatefulTestFunction for BuggyStore {
+        type State = Mutex<HashMap<u8, u8>>;
+        type Command = Command;
+        type Return = Option<u8>;
+        const TEST_ID: &'static str = "kv_store";
+
+        fn begin(&self) -> Result<Self::State, String> {
+            Ok(Mutex::new(HashMap::new()))
+        }
+
+        fn execute(
+            &self,
+            state: &Self::State,
+            command: Command,
+        ) -> Result<Option<u8>, String> {
+            let mut map = state.lock().unwrap();
+            Ok(match command {
+                Command::Put(key, value) => map.insert(key, value),
+                Command::Get(key) => map.get(&key).copied(),
+                Command::Delete(3) => map.get(&3).copied(),
+                Command::Delete(key) => map.remove(&key),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn sequences_are_run_in_sessions_and_shrunk() {
+        let endpoint = StatefulTestRunner::new(BuggyStore)
+            .spawn("127.0.0.1:0")
+            .await
+            .unwrap();
+        let test = StateMachineTest::new(KvStore { endpoint });
+        let mut qc = QuickCheck::new();
+        let r = qc.quicktest(test).await.unwrap_err();
+        assert!(matches!(r.failure, Some(TestFailure::Property(_))));
+        let commands: Vec<Command> =
+            rmp_serde::from_slice(&r.test_data.unwrap()).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert!(matches!(commands[0], Command::Put(3, _)));
+        assert_eq!(commands[1], Command::Delete(3));
+        assert!(matches!(commands[2], Command::Get(3) | Command::Delete(3)));
+    }
+}
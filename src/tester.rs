@@ -1,17 +1,33 @@
+use std::collections::hash_map::{Entry, HashMap};
 use std::env;
 use std::fmt::Debug;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
+use futures::channel::{mpsc, oneshot};
+use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
 
 // Use the gRPC client types from the quickcheck_rpc crate.
 use quickcheck_rpc::{
-    execute_response::TestStatus as ProtoStatus,
-    test_runner_client::TestRunnerClient, ExecuteRequest,
+    execute_response::TestStatus as ProtoStatus, BeginSessionRequest,
+    EndSessionRequest, ExecuteBatchRequest, ExecuteRequest, ExecuteResponse,
+    codec::Codec, run_response::Event,
+    schema::{mismatches, schema_of, Schema},
+    security::Tls,
+    DescribeRequest, RunRequest, RunResponse,
 };
+use tonic::{Code, Streaming};
 
 use crate::{
+    composite::ComparisonReport,
+    corpus::RegressionCorpus,
+    coverage::Coverage,
+    database::{Example, ExampleDatabase},
     tester::Status::{Discard, Fail, Pass},
+    transport::{self, Channels, Security, CHANNELS, SECURITY, VERSIONS},
     Arbitrary, Gen,
 };
 
@@ -21,74 +37,457 @@ pub struct QuickCheck {
     max_tests: u64,
     min_tests_passed: u64,
     rng: Gen,
+    max_size: usize,
+    sizing: Sizing,
+    batch_size: usize,
+    streaming: bool,
+    security: Security,
+    database: Option<ExampleDatabase>,
+    regressions: Option<RegressionCorpus>,
+    requirements: Vec<(String, f64)>,
+    coverage: Coverage,
 }
 
 // --- 配置函数 (qc_*) 保持不变 ---
-fn qc_tests() -> u64 {
-    let default = 100;
-    match env::var("QUICKCHECK_TESTS") {
+fn qc_batch_size() -> usize {
+    let default = 1;
+    match env::var("QUICKCHECK_BATCH_SIZE") {
         Ok(val) => val.parse().unwrap_or(default),
         Err(_) => default,
     }
 }
 
-fn qc_max_tests() -> u64 {
-    let default = 10_000;
-    match env::var("QUICKCHECK_MAX_TESTS") {
+fn qc_streaming() -> bool {
+    let default = false;
+    match env::var("QUICKCHECK_STREAMING") {
         Ok(val) => val.parse().unwrap_or(default),
         Err(_) => default,
     }
 }
 
-fn qc_gen_size() -> usize {
-    let default = 100;
-    match env::var("QUICKCHECK_GENERATOR_SIZE") {
-        Ok(val) => val.parse().unwrap_or(default),
-        Err(_) => default,
-    }
+fn qc_database() -> Option<ExampleDatabase> {
+    env::var_os("QUICKCHECK_DATABASE").map(ExampleDatabase::new)
 }
 
-fn qc_min_tests_passed() -> u64 {
-    let default = 0;
-    match env::var("QUICKCHECK_MIN_TESTS_PASSED") {
-        Ok(val) => val.parse().unwrap_or(default),
-        Err(_) => default,
+fn qc_token() -> Option<String> {
+    env::var("QUICKCHECK_TOKEN").ok()
+}
+
+fn qc_regressions() -> Option<RegressionCorpus> {
+    match env::var_os("QUICKCHECK_REGRESSIONS") {
+        Some(dir) if dir.is_empty() => None,
+        Some(dir) => Some(RegressionCorpus::new(dir)),
+        None => Some(RegressionCorpus::new("quickcheck-regressions")),
     }
 }
 
+/// A sizing function decides the `Gen` size of each test case.
+///
+/// It is given the number of tests passed so far, the number of tests to run
+/// and the maximum size, and returns the size for the next case.
+pub type Sizing = fn(passed: u64, tests: u64, max_size: usize) -> usize;
+
+/// Ramps the size linearly from small to `max_size` across the run, like
+/// Haskell's QuickCheck.
+///
+/// Early cases are small, so tiny edge cases are hit first, while the last
+/// cases use the full `max_size`. This is the default sizing function.
+pub fn linear_size(passed: u64, tests: u64, max_size: usize) -> usize {
+    let size = (passed + 1).saturating_mul(max_size as u64) / tests.max(1);
+    (size as usize).clamp(1, max_size.max(1))
+}
+
+/// Uses `max_size` for every case.
+pub fn constant_size(_passed: u64, _tests: u64, max_size: usize) -> usize {
+    max_size
+}
+
 impl Default for QuickCheck {
     fn default() -> Self {
         Self::new()
     }
 }
 
-// (Full implementation for QuickCheck and its methods is omitted for brevity)
-#[allow(dead_code)]
 impl QuickCheck {
+    /// Creates a new QuickCheck value.
+    ///
+    /// This can be used to run QuickCheck on things that implement `Testable`.
+    /// You may also adjust the configuration, such as the number of tests to
+    /// run.
+    ///
+    /// By default, the maximum number of passed tests is set to `100`, the max
+    /// number of overall tests is set to `10000` and the generator is created
+    /// with a maximum size of `100`, which cases ramp up to with
+    /// `linear_size`. If the `QUICKCHECK_DATABASE` environment variable
+    /// is set, failing examples are stored in the directory it names.
+    /// Minimal failing arguments are kept in a regression corpus in the
+    /// `quickcheck-regressions` directory, or in the directory named by the
+    /// `QUICKCHECK_REGRESSIONS` environment variable; setting it to an empty
+    /// string disables the corpus.
     pub fn new() -> Self {
+        let database = qc_database();
+        let regressions = qc_regressions();
+
         Self {
             tests: 100,
             max_tests: 10000,
             min_tests_passed: 0,
             rng: Gen::new(100),
+            max_size: 100,
+            sizing: linear_size,
+            batch_size: qc_batch_size().max(1),
+            streaming: qc_streaming(),
+            security: Security { tls: None, token: qc_token() },
+            database,
+            regressions,
+            requirements: vec![],
+            coverage: Coverage::default(),
         }
     }
+
+    /// Set the random number generator to be used by QuickCheck.
+    ///
+    /// The size of `rng` becomes the maximum size passed to the sizing
+    /// function.
+    pub fn set_rng(self, rng: Gen) -> Self {
+        Self { max_size: rng.size(), rng, ..self }
+    }
+
+    /// Set the function that decides the size of each test case.
+    ///
+    /// The default is `linear_size`. Use `constant_size` to generate every
+    /// case at the maximum size.
+    pub fn sizing(mut self, sizing: Sizing) -> Self {
+        self.sizing = sizing;
+        self
+    }
+
+    /// Set the number of cases generated and checked together.
+    ///
+    /// Remote tests send each chunk to the runner in one `ExecuteBatch`
+    /// call, which saves a round trip per case for cheap properties. Results
+    /// are still handled in generation order, so the first failing case of a
+    /// chunk is the one that is shrunk and reported. The default is `1`, or
+    /// the value of the `QUICKCHECK_BATCH_SIZE` environment variable.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set whether remote tests talk to each runner over one `Run` stream.
+    ///
+    /// When enabled, every case and shrink candidate sent to a runner during
+    /// a run goes over a single long-lived bidirectional stream instead of
+    /// its own `Execute` call, and messages the runner logs are printed as
+    /// they arrive. Runners that don't implement `Run` are called with
+    /// `Execute` as usual. The default is off, or the value of the
+    /// `QUICKCHECK_STREAMING` environment variable.
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Set the TLS settings used to connect to runners at `https`
+    /// endpoints.
+    ///
+    /// Runners with self-signed certificates need `Tls::ca_cert`, and
+    /// runners that require mutual TLS need `Tls::identity`.
+    pub fn tls(mut self, tls: Tls) -> Self {
+        self.security.tls = Some(tls);
+        self
+    }
+
+    /// Set the shared secret sent to runners with every request, which
+    /// runners started with a `token` require. The default is the value of
+    /// the `QUICKCHECK_TOKEN` environment variable, if it is set.
+    pub fn token<S: Into<String>>(mut self, secret: S) -> Self {
+        self.security.token = Some(secret.into());
+        self
+    }
+
+    /// Set the number of tests to run.
+    ///
+    /// This actually refers to the maximum number of *passed* tests that
+    /// can occur. Namely, if a test causes a failure, future testing on that
+    /// property stops. Additionally, if tests are discarded, there may be
+    /// fewer than `tests` passed.
+    pub fn tests(mut self, tests: u64) -> Self {
+        self.tests = tests;
+        self
+    }
+
+    /// Set the maximum number of tests to run.
+    ///
+    /// The number of invocations of a property will never exceed this number.
+    /// This is necessary to cap the number of tests because QuickCheck
+    /// properties can discard tests.
+    pub fn max_tests(mut self, max_tests: u64) -> Self {
+        self.max_tests = max_tests;
+        self
+    }
+
+    /// Set the minimum number of tests that needs to pass.
+    ///
+    /// This actually refers to the minimum number of *valid* *passed* tests
+    /// that needs to pass for the property to be considered successful.
+    pub fn min_tests_passed(mut self, min_tests_passed: u64) -> Self {
+        self.min_tests_passed = min_tests_passed;
+        self
+    }
+
+    /// Set the database used to store and replay failing examples.
+    ///
+    /// Before any random case is generated, every example saved for the
+    /// property's test ID is replayed, and examples that no longer fail are
+    /// deleted. When a random case fails, the choice buffer that generated it
+    /// is shrunk and saved, so the failure is re-checked on every later run
+    /// regardless of what the random cases turn out to be.
+    pub fn database(mut self, database: ExampleDatabase) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Set the regression corpus, or disable it with `None`.
+    ///
+    /// Every entry of the property's corpus file is re-executed before any
+    /// random case, and the minimal arguments of every new failure are
+    /// appended to it. Only properties that can decode their arguments take
+    /// part; see `RemoteTest::args_decoder`. Entries that no longer decode
+    /// are removed from the corpus.
+    pub fn regressions(
+        mut self,
+        regressions: Option<RegressionCorpus>,
+    ) -> Self {
+        self.regressions = regressions;
+        self
+    }
+
+    /// Require that at least `pct` percent of passed tests carry `label`.
+    ///
+    /// Labels may be attached locally with `TestResult::label` and friends,
+    /// or by a remote runner. If the requirement isn't met at the end of the
+    /// run, the property fails with `TestFailure::Coverage`.
+    pub fn cover<S: Into<String>>(mut self, pct: f64, label: S) -> Self {
+        self.requirements.push((label.into(), pct));
+        self
+    }
+
+    /// Returns the label and table statistics of the last run.
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+
+    /// Tests a property and returns the result.
+    ///
+    /// The result returned is either the number of tests passed or a witness
+    /// of failure.
+    ///
+    /// Every runner the property calls is asked for one session, which is
+    /// shared by all cases of the run and ended when the run finishes, so
+    /// that costly runner setup happens once per run.
     pub async fn quicktest<A>(&mut self, f: A) -> Result<u64, TestResult>
     where
         A: Testable,
     {
+        let sessions = Arc::new(Mutex::new(HashMap::new()));
+        let streams = Streams::default();
+        let streaming = self.streaming;
+        let security = self.security.clone();
+        let run = async {
+            let run = SESSIONS.scope(sessions.clone(), self.run(f));
+            let result = if streaming {
+                STREAMS.scope(streams.clone(), run).await
+            } else {
+                run.await
+            };
+            // Closing the streams first lets runners finish their last
+            // cases.
+            streams.lock().await.clear();
+            end_sessions(&sessions).await;
+            result
+        };
+        // Connections, and runners spawned for the run, live until its
+        // sessions are ended.
+        let run = CHANNELS.scope(Channels::default(), run);
+        let run = VERSIONS.scope(Default::default(), run);
+        SECURITY.scope(security, run).await
+    }
+
+    async fn run<A>(&mut self, f: A) -> Result<u64, TestResult>
+    where
+        A: Testable,
+    {
+        self.coverage = Coverage::default();
+        for (label, pct) in &self.requirements {
+            self.coverage.require(*pct, label);
+        }
+
+        if let Err(msg) = f.check_schema().await {
+            return Err(TestResult {
+                status: Fail,
+                failure: Some(TestFailure::Schema(msg)),
+                ..TestResult::default()
+            });
+        }
+
+        let test_id = f.test_id();
+        let database = match (&self.database, &test_id) {
+            (Some(database), Some(test_id)) => Some((database, test_id)),
+            _ => None,
+        };
+        let regressions = match (&self.regressions, &test_id) {
+            (Some(regressions), Some(test_id)) if f.replays_regressions() => {
+                Some((regressions, test_id))
+            }
+            _ => None,
+        };
+
+        if let Some((regressions, test_id)) = regressions {
+            // Entries that no longer decode, say because the arguments
+            // changed shape, can never be replayed again.
+            let entries = regressions.load(test_id);
+            let count = entries.len();
+            let (entries, cases): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .filter_map(|test_data| {
+                    let args = f.decode_args(&test_data)?;
+                    Some((test_data, args))
+                })
+                .unzip();
+            if entries.len() < count {
+                if let Err(err) = regressions.replace(test_id, &entries) {
+                    eprintln!(
+                        "[quickcheck] failed to prune regressions in {}: {}",
+                        regressions.path(test_id).display(),
+                        err
+                    );
+                }
+            }
+            for args in cases {
+                let r = f.result(&args).await;
+                if r.is_failure() {
+                    return Err(r);
+                }
+            }
+        }
+        if let Some((database, test_id)) = database {
+            for example in database.fetch(test_id) {
+                // An example saved with a larger maximum size must not
+                // generate values larger than this run allows.
+                let size = example.size.min(self.max_size);
+                let mut g = Gen::from_choices(size, example.choices.clone());
+                let args = A::Args::arbitrary(&mut g);
+                let mut r = f.result(&args).await;
+                if r.is_failure() {
+                    r.size = Some(size);
+                    return Err(r);
+                }
+                // The failure has been fixed, so there is no point in
+                // replaying it again.
+                if let Err(err) = database.delete(test_id, &example) {
+                    eprintln!(
+                        "[quickcheck] failed to delete example from {}: {}",
+                        database.dir().display(),
+                        err
+                    );
+                }
+            }
+        }
+
         let mut n_tests_passed = 0;
-        for _ in 0..self.max_tests {
-            if n_tests_passed >= self.tests {
-                break;
+        let mut n_tests = 0;
+        while n_tests < self.max_tests && n_tests_passed < self.tests {
+            // Never generate more cases than could still be needed.
+            let n = (self.batch_size as u64)
+                .min(self.tests - n_tests_passed)
+                .min(self.max_tests - n_tests);
+            let mut chunk = Vec::with_capacity(n as usize);
+            let mut cases = Vec::with_capacity(n as usize);
+            for i in 0..n {
+                let size = (self.sizing)(
+                    n_tests_passed + i,
+                    self.tests,
+                    self.max_size,
+                );
+                self.rng.set_size(size);
+                if database.is_some() {
+                    self.rng.start_recording();
+                }
+                chunk.push(A::Args::arbitrary(&mut self.rng));
+                cases.push((size, self.rng.take_choices()));
             }
-            let args = A::Args::arbitrary(&mut self.rng);
-            match f.result(&args).await {
-                TestResult { status: Pass, .. } => n_tests_passed += 1,
-                TestResult { status: Discard, .. } => (),
-                r @ TestResult { status: Fail, .. } => return Err(r),
+            n_tests += n;
+            let results = f.results(&chunk).await;
+            for (r, (size, choices)) in results.into_iter().zip(cases) {
+                match r {
+                    r @ TestResult { status: Pass, .. } => {
+                        self.coverage.record(&r);
+                        n_tests_passed += 1;
+                    }
+                    TestResult { status: Discard, .. } => (),
+                    mut r @ TestResult { status: Fail, .. } => {
+                        r.size = Some(size);
+                        if let (Some((database, test_id)), Some(choices)) =
+                            (database, choices)
+                        {
+                            let example = Example { size, choices };
+                            let example =
+                                shrink_example(&f, &r, example).await;
+                            if let Err(err) =
+                                database.save(test_id, &example)
+                            {
+                                eprintln!(
+                                    "[quickcheck] failed to save example to \
+                                     {}: {}",
+                                    database.dir().display(),
+                                    err
+                                );
+                            }
+                        }
+                        // Failing to reach a runner is not a regression.
+                        let entry = r
+                            .test_data
+                            .as_deref()
+                            .filter(|_| !r.is_error())
+                            .and_then(|test_data| f.corpus_entry(test_data));
+                        if let (Some((regressions, test_id)), Some(entry)) =
+                            (regressions, entry)
+                        {
+                            if let Err(err) =
+                                regressions.append(test_id, &entry)
+                            {
+                                eprintln!(
+                                    "[quickcheck] failed to save regression \
+                                     to {}: {}",
+                                    regressions.path(test_id).display(),
+                                    err
+                                );
+                            }
+                        }
+                        return Err(r);
+                    }
+                }
             }
         }
+
+        let insufficient = self.coverage.insufficient();
+        if n_tests_passed > 0 && !insufficient.is_empty() {
+            let msg = insufficient
+                .iter()
+                .map(|(label, required, actual)| {
+                    format!(
+                        "only {:.1}% {}, but expected at least {:.1}%",
+                        actual, label, required
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(TestResult {
+                status: Fail,
+                failure: Some(TestFailure::Coverage(msg)),
+                ..TestResult::default()
+            });
+        }
         Ok(n_tests_passed)
     }
     pub async fn quickcheck<A>(&mut self, f: A)
@@ -98,7 +497,16 @@ impl QuickCheck {
         // Ignore log init failures, implying it has already been done.
         let _ = crate::env_logger_init();
 
-        let n_tests_passed = match self.quicktest(f).await {
+        let result = self.quicktest(f).await;
+        let covered = match result {
+            Ok(_) => true,
+            Err(ref r) => matches!(r.failure, Some(TestFailure::Coverage(_))),
+        };
+        if covered && !self.coverage.is_empty() {
+            print!("{}", self.coverage.report());
+        }
+
+        let n_tests_passed = match result {
             Ok(n_tests_passed) => n_tests_passed,
             Err(result) => {
                 if result.is_error() { // is_error() checks for TestFailure::Runtime
@@ -122,6 +530,61 @@ impl QuickCheck {
         }
     }
 }
+
+/// The most candidate choice buffers tried when shrinking an example.
+const MAX_EXAMPLE_SHRINKS: usize = 500;
+
+/// Shrinks the choice buffer of a failing example before it is saved.
+///
+/// The arguments reported for a failure have been shrunk, but the buffer
+/// that generated them has not. Chunks of the buffer are deleted or zeroed,
+/// largest first, and each candidate whose arguments still fail the way
+/// `failure` did, with the same kind of `TestFailure`, is kept, so that the
+/// saved example replays to small arguments too. Candidates are checked
+/// with `Testable::check_once`, so their failures are not shrunk in turn.
+async fn shrink_example<A: Testable>(
+    f: &A,
+    failure: &TestResult,
+    mut example: Example,
+) -> Example {
+    let kind = failure.failure.as_ref().map(mem::discriminant);
+    let fails = |choices: Vec<u8>| async move {
+        let mut g = Gen::from_choices(example.size, choices);
+        let r = f.check_once(&A::Args::arbitrary(&mut g)).await;
+        r.is_failure() && r.failure.as_ref().map(mem::discriminant) == kind
+    };
+    let mut tries = 0;
+    let mut chunk = example.choices.len().next_power_of_two() / 2;
+    while chunk > 0 && tries < MAX_EXAMPLE_SHRINKS {
+        let mut i = 0;
+        while i < example.choices.len() && tries < MAX_EXAMPLE_SHRINKS {
+            let end = (i + chunk).min(example.choices.len());
+            let mut deleted = example.choices.clone();
+            deleted.drain(i..end);
+            tries += 1;
+            if fails(deleted.clone()).await {
+                example.choices = deleted;
+                continue;
+            }
+            if example.choices[i..end].iter().any(|&b| b != 0) {
+                let mut zeroed = example.choices.clone();
+                zeroed[i..end].fill(0);
+                tries += 1;
+                if fails(zeroed.clone()).await {
+                    example.choices = zeroed;
+                }
+            }
+            i = end;
+        }
+        chunk /= 2;
+    }
+    // Draws past the end of a buffer are zero, so trailing zeros are noise.
+    while example.choices.last() == Some(&0) {
+        example.choices.pop();
+    }
+    example
+}
+
 pub async fn quickcheck<A: Testable + Send + Sync>(a: A) {
     QuickCheck::new().quickcheck(a).await;
 }
@@ -130,8 +593,10 @@ pub async fn quickcheck<A: Testable + Send + Sync>(a: A) {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum TestFailure {
     Property(Option<String>), // Detail from runner
-    Comparison,               // No extra detail needed, or a default message
+    Comparison(ComparisonReport), // What each implementation returned
     Runtime(String),          // Detail of the runtime error
+    Coverage(String),         // Which coverage requirements were not met
+    Schema(String),           // How the runner's types differ from ours
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
@@ -141,6 +606,21 @@ pub struct TestResult {
     #[serde(default)] // Ensure default is handled for deserialization
     pub failure: Option<TestFailure>, // New field, replaces `err` and `err_type`
     pub return_value: Option<Vec<u8>>,
+    /// The arguments, encoded as they were sent to the runner.
+    #[serde(default)]
+    pub test_data: Option<Vec<u8>>,
+    /// Labels classifying this case, for coverage statistics.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Values added to named tables, as `(table, value)` pairs.
+    #[serde(default)]
+    pub tables: Vec<(String, String)>,
+    /// Coverage requirements, as `(label, percentage)` pairs.
+    #[serde(default)]
+    pub coverage: Vec<(String, f64)>,
+    /// The `Gen` size the failing case was generated at.
+    #[serde(default)]
+    pub size: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
@@ -189,6 +669,8 @@ impl TestResult {
             arguments: vec![],
             failure: None,
             return_value: None,
+            test_data: None,
+            ..TestResult::default()
         }
     }
 
@@ -201,13 +683,15 @@ impl TestResult {
             arguments: vec![],
             failure: if b { None } else { Some(TestFailure::Property(None)) },
             return_value: None,
+            test_data: None,
+            ..TestResult::default()
         }
     }
 
     // must_fail 需要用 spawn_blocking 改造，因为它依赖 panic::catch_unwind
     // 为简化，此处暂时注释，实际项目中需要异步化改造
-    /// Tests if a "procedure" fails when executed. The test passes only if
-    /// `f` generates a task failure during its execution.
+    // Tests if a "procedure" fails when executed. The test passes only if
+    // `f` generates a task failure during its execution.
     /*
     pub fn must_fail<T, F>(f: F) -> TestResult
     where
@@ -220,6 +704,62 @@ impl TestResult {
     }
     */
 
+    /// Attaches a label to this case.
+    ///
+    /// The distribution of labels over all passed cases is printed at the
+    /// end of the run.
+    pub fn label<S: Into<String>>(mut self, label: S) -> TestResult {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Attaches a label to this case if `cond` holds.
+    pub fn classify<S: Into<String>>(self, cond: bool, label: S) -> TestResult {
+        if cond {
+            self.label(label)
+        } else {
+            self
+        }
+    }
+
+    /// Labels this case with the `Debug` representation of a value.
+    pub fn collect<T: Debug>(self, value: T) -> TestResult {
+        self.label(format!("{:?}", value))
+    }
+
+    /// Adds values to the named table.
+    ///
+    /// Each table is printed separately at the end of the run, with the
+    /// percentage of each value among all values added to it.
+    pub fn tabulate<S, I>(mut self, table: S, values: I) -> TestResult
+    where
+        S: Into<String>,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let table = table.into();
+        for value in values {
+            self.tables.push((table.clone(), value.into()));
+        }
+        self
+    }
+
+    /// Classifies this case like `classify`, and requires that at least `pct`
+    /// percent of all passed cases carry `label`.
+    ///
+    /// If the requirement isn't met at the end of the run, the property
+    /// fails.
+    pub fn cover<S: Into<String>>(
+        mut self,
+        pct: f64,
+        cond: bool,
+        label: S,
+    ) -> TestResult {
+        let label = label.into();
+        self.coverage.push((label.clone(), pct));
+        self.classify(cond, label)
+    }
+
     /// Returns `true` if and only if this test result describes a failing
     /// test.
     pub fn is_failure(&self) -> bool {
@@ -231,8 +771,12 @@ impl TestResult {
     pub fn is_error(&self) -> bool {
         matches!(self.failure, Some(TestFailure::Runtime(_)))
     }
-    fn failed_msg(&self) -> String {
-        let arguments_msg = format!("Arguments: ({})", self.arguments.join(", "));
+    pub(crate) fn failed_msg(&self) -> String {
+        let mut arguments_msg =
+            format!("Arguments: ({})", self.arguments.join(", "));
+        if let Some(size) = self.size {
+            arguments_msg.push_str(&format!(" Size: {size}."));
+        }
         match &self.failure {
             Some(TestFailure::Runtime(err_msg)) => format!(
                 "[quickcheck] TEST FAILED (runtime error). {arguments_msg}\nError: {err_msg}"
@@ -243,8 +787,14 @@ impl TestResult {
             Some(TestFailure::Property(None)) => format!(
                 "[quickcheck] TEST FAILED. {arguments_msg}"
             ),
-            Some(TestFailure::Comparison) => format!(
-                "[quickcheck] TEST FAILED (comparison). {arguments_msg}\nError: Comparison function returned false"
+            Some(TestFailure::Coverage(err_msg)) => format!(
+                "[quickcheck] TEST FAILED (insufficient coverage).\nError: {err_msg}"
+            ),
+            Some(TestFailure::Schema(err_msg)) => format!(
+                "[quickcheck] TEST FAILED (schema mismatch).\nError: {err_msg}"
+            ),
+            Some(TestFailure::Comparison(report)) => format!(
+                "[quickcheck] TEST FAILED (comparison). {arguments_msg}\nError: {report}"
             ),
             None => format!("[quickcheck] TEST PASSED. {arguments_msg}"), // Should not happen if status is Fail
         }
@@ -273,8 +823,71 @@ impl From<bool> for TestResult {
 pub trait Testable: 'static + Send + Sync {
     /// The argument type for this testable
     type Args: Arbitrary + Debug + Clone + Send + Sync + 'static;
-    
+
+    /// A stable identifier for this property.
+    ///
+    /// It keys the examples stored in an `ExampleDatabase`. Properties without
+    /// an identifier are never saved to or replayed from a database.
+    fn test_id(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether failures of this property are kept in, and replayed from, a
+    /// regression corpus.
+    ///
+    /// Properties that return `true` must be able to decode their entries
+    /// with `decode_args`: entries it rejects are removed from the corpus.
+    fn replays_regressions(&self) -> bool {
+        false
+    }
+
+    /// Decodes arguments from an entry of a regression corpus.
+    fn decode_args(&self, _test_data: &[u8]) -> Option<Self::Args> {
+        None
+    }
+
+    /// Turns the `test_data` of a failure into an entry of a regression
+    /// corpus, which is MessagePack with named fields.
+    ///
+    /// The default assumes `test_data` is encoded that way already.
+    fn corpus_entry(&self, test_data: &[u8]) -> Option<Vec<u8>> {
+        Some(test_data.to_vec())
+    }
+
     async fn result(&self, args: &Self::Args) -> TestResult;
+
+    /// Checks the arguments once, without shrinking a failure.
+    ///
+    /// The default is `result`, which suits properties that don't shrink
+    /// their failures themselves.
+    async fn check_once(&self, args: &Self::Args) -> TestResult {
+        self.result(args).await
+    }
+
+    /// Checks that the types this property exchanges with its runner agree
+    /// with the runner's, before any case runs, describing every mismatch.
+    ///
+    /// The default has nothing to check.
+    async fn check_schema(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Checks a chunk of cases, returning their results in order up to and
+    /// including the first failure, which is shrunk like in `result`.
+    ///
+    /// The default checks the cases one at a time with `result`.
+    async fn results(&self, args: &[Self::Args]) -> Vec<TestResult> {
+        let mut results = Vec::with_capacity(args.len());
+        for args in args {
+            let r = self.result(args).await;
+            let failed = r.is_failure();
+            results.push(r);
+            if failed {
+                break;
+            }
+        }
+        results
+    }
 }
 
 /// A new trait to define a remote test and its argument structure.
@@ -295,6 +908,517 @@ pub trait RemoteTest: Send + Sync {
 
     /// The network address of the gRPC runner server.
     fn endpoint(&self) -> &str;
+
+    /// How arguments and return values are encoded for the runner.
+    ///
+    /// The default is MessagePack with named fields, which every runner
+    /// supports.
+    fn codec(&self) -> Codec {
+        Codec::default()
+    }
+
+    /// How to decode arguments, which keeps failures of this test in the
+    /// regression corpus so that they are replayed on every later run.
+    ///
+    /// The default is `None`, which leaves the test out of the corpus. Tests
+    /// whose arguments implement `Deserialize` opt in with
+    /// `Some(|codec, test_data| codec.decode(test_data))`.
+    fn args_decoder(&self) -> Option<ArgsDecoder<Self::Args>> {
+        None
+    }
+
+    /// The schema of the arguments, checked against the runner's before a
+    /// run, usually `Some(schema_of::<Self::Args>())`.
+    ///
+    /// Arguments only need `Serialize`, so this cannot be derived for them.
+    fn args_schema(&self) -> Option<Schema> {
+        None
+    }
+}
+
+/// Decodes arguments encoded with the given codec.
+pub type ArgsDecoder<A> = fn(Codec, &[u8]) -> Result<A, String>;
+
+/// The runner sessions of a run, keyed by endpoint and test ID.
+///
+/// An empty session ID marks a runner that could not begin a session.
+type Sessions = Arc<Mutex<HashMap<(String, String), String>>>;
+
+/// The `Run` streams of a run, keyed by endpoint.
+///
+/// `None` marks a runner that does not implement `Run`.
+type Streams =
+    Arc<tokio::sync::Mutex<HashMap<String, Option<Arc<RunStream>>>>>;
+
+tokio::task_local! {
+    /// The runner sessions of the run in progress.
+    static SESSIONS: Sessions;
+
+    /// The `Run` streams of the run in progress, if streaming is enabled.
+    static STREAMS: Streams;
+}
+
+/// Returns the session of the run in progress for the given runner and test,
+/// beginning it on first use.
+///
+/// Outside of a run, or if the runner does not support sessions, this is the
+/// empty string and cases are executed outside of any session.
+async fn run_session(
+    client: &mut transport::Client,
+    endpoint: &str,
+    test_id: &str,
+) -> String {
+    let sessions = match SESSIONS.try_with(Arc::clone) {
+        Ok(sessions) if client.extended() => sessions,
+        _ => return String::new(),
+    };
+    let key = (endpoint.to_string(), test_id.to_string());
+    if let Some(session_id) = sessions.lock().unwrap().get(&key) {
+        return session_id.clone();
+    }
+    let request = BeginSessionRequest { test_id: test_id.to_string() };
+    let session_id = match client.begin_session(request).await {
+        Ok(response) => response.into_inner().session_id,
+        Err(_) => String::new(),
+    };
+    // A concurrent case, such as another implementation of a composite test
+    // on the same runner, may have begun a session first.
+    let existing = match sessions.lock().unwrap().entry(key) {
+        Entry::Occupied(entry) => Some(entry.get().clone()),
+        Entry::Vacant(entry) => {
+            entry.insert(session_id.clone());
+            None
+        }
+    };
+    match existing {
+        Some(existing) => {
+            if !session_id.is_empty() {
+                let request = EndSessionRequest { session_id };
+                let _ = client.end_session(request).await;
+            }
+            existing
+        }
+        None => session_id,
+    }
+}
+
+/// Ends every session begun during a run.
+async fn end_sessions(sessions: &Sessions) {
+    let sessions = std::mem::take(&mut *sessions.lock().unwrap());
+    for ((endpoint, _), session_id) in sessions {
+        if session_id.is_empty() {
+            continue;
+        }
+        if let Ok(mut client) = transport::client(&endpoint).await {
+            let _ = client.end_session(EndSessionRequest { session_id }).await;
+        }
+    }
+}
+
+/// A `Run` stream to a runner, over which the cases of a run are executed.
+struct RunStream {
+    client: transport::Client,
+    cases: mpsc::UnboundedSender<RunRequest>,
+    pending: Arc<Mutex<Pending>>,
+    next_case: AtomicU64,
+}
+
+/// The cases sent over a stream that are still waiting for their results.
+#[derive(Default)]
+struct Pending {
+    waiting: HashMap<u64, oneshot::Sender<Result<ExecuteResponse, String>>>,
+    /// Why the stream closed, once it has.
+    closed: Option<String>,
+}
+
+impl RunStream {
+    /// Opens a stream to the runner at `endpoint`, or returns `None` if the
+    /// runner does not implement `Run` or speaks protocol version 1.
+    async fn open(endpoint: &str) -> Result<Option<RunStream>, String> {
+        let mut client = transport::client(endpoint).await?;
+        if !client.extended() {
+            return Ok(None);
+        }
+        let (cases, requests) = mpsc::unbounded();
+        let responses = match client.run(requests).await {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == Code::Unimplemented => {
+                return Ok(None)
+            }
+            Err(status) => return Err(status.to_string()),
+        };
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let endpoint = endpoint.to_string();
+        tokio::spawn(receive(endpoint, responses, pending.clone()));
+        Ok(Some(RunStream {
+            client,
+            cases,
+            pending,
+            next_case: AtomicU64::new(0),
+        }))
+    }
+
+    /// Sends a case over the stream and waits for its result.
+    async fn execute(
+        &self,
+        case: ExecuteRequest,
+    ) -> Result<ExecuteResponse, String> {
+        let case_id = self.next_case.fetch_add(1, Ordering::Relaxed);
+        let (result, received) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if let Some(closed) = &pending.closed {
+                return Err(closed.clone());
+            }
+            pending.waiting.insert(case_id, result);
+        }
+        let request = RunRequest { case_id, case: Some(case) };
+        self.cases.unbounded_send(request).map_err(|e| e.to_string())?;
+        match received.await {
+            Ok(result) => result,
+            // The stream closed before the result arrived.
+            Err(_) => Err(self
+                .pending
+                .lock()
+                .unwrap()
+                .closed
+                .clone()
+                .unwrap_or_default()),
+        }
+    }
+}
+
+/// Hands the results arriving on a stream to the cases waiting for them, and
+/// prints the messages the runner logs.
+async fn receive(
+    endpoint: String,
+    mut responses: Streaming<RunResponse>,
+    pending: Arc<Mutex<Pending>>,
+) {
+    let closed = loop {
+        let event = match responses.message().await {
+            Ok(Some(response)) => response.event,
+            Ok(None) => break "Runner closed the stream".to_string(),
+            Err(status) => break status.to_string(),
+        };
+        match event {
+            Some(Event::Result(result)) => {
+                let waiting =
+                    pending.lock().unwrap().waiting.remove(&result.case_id);
+                let result = match (result.response, result.error) {
+                    (_, Some(error)) => Err(error),
+                    (Some(response), None) => Ok(response),
+                    (None, None) => Err("Runner sent an empty result".into()),
+                };
+                if let Some(waiting) = waiting {
+                    let _ = waiting.send(result);
+                }
+            }
+            Some(Event::Log(log)) => {
+                eprintln!(
+                    "[{}] case {}: {}",
+                    endpoint, log.case_id, log.message
+                );
+            }
+            None => {}
+        }
+    };
+    let mut pending = pending.lock().unwrap();
+    pending.closed = Some(closed);
+    pending.waiting.clear();
+}
+
+/// Returns the `Run` stream of the run in progress to the given runner,
+/// opening it on first use.
+///
+/// This is `None` if streaming is disabled or the runner does not implement
+/// `Run`.
+async fn run_stream(endpoint: &str) -> Result<Option<Arc<RunStream>>, String> {
+    let streams = match STREAMS.try_with(Arc::clone) {
+        Ok(streams) => streams,
+        Err(_) => return Ok(None),
+    };
+    let mut streams = streams.lock().await;
+    if let Some(stream) = streams.get(endpoint) {
+        return Ok(stream.clone());
+    }
+    let stream = RunStream::open(endpoint).await?.map(Arc::new);
+    streams.insert(endpoint.to_string(), stream.clone());
+    Ok(stream)
+}
+
+/// Returns the `Run` stream to a runner, if cases should be sent over one,
+/// and a client for its other calls.
+async fn connect(
+    endpoint: &str,
+) -> Result<(Option<Arc<RunStream>>, transport::Client), String> {
+    match run_stream(endpoint).await? {
+        Some(stream) => {
+            let client = stream.client.clone();
+            Ok((Some(stream), client))
+        }
+        None => {
+            let client = transport::client(endpoint).await?;
+            Ok((None, client))
+        }
+    }
+}
+
+/// Executes a remote test once on the given arguments, without shrinking.
+///
+/// An `Err` means the runner could not be called at all.
+pub(crate) async fn execute_remote<Rt: RemoteTest>(
+    test: &Rt,
+    args: &Rt::Args,
+) -> Result<TestResult, String> {
+    let codec = test.codec();
+    let test_data = codec.encode(args)?;
+    let response =
+        execute_encoded(test.endpoint(), Rt::TEST_ID, codec, test_data.clone())
+            .await?;
+    Ok(to_test_result(args, test_data, response))
+}
+
+/// Executes one case of a test on arguments that are already encoded.
+///
+/// An `Err` means the runner could not be called at all.
+pub(crate) async fn execute_encoded(
+    endpoint: &str,
+    test_id: &str,
+    codec: Codec,
+    test_data: Vec<u8>,
+) -> Result<ExecuteResponse, String> {
+    let (stream, mut client) = connect(endpoint).await?;
+    let session_id = run_session(&mut client, endpoint, test_id).await;
+    let request = ExecuteRequest {
+        test_id: test_id.to_string(),
+        test_data,
+        session_id,
+        encoding: codec.encoding().into(),
+        version: client.version(),
+    };
+    let response = match stream {
+        Some(stream) => stream.execute(request).await?,
+        None => client
+            .execute(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|e| e.to_string())?,
+    };
+    client.check_response(&response)?;
+    Ok(response)
+}
+
+/// Asks a runner for the schemas of a test's arguments and return value.
+///
+/// Runners that do not implement `Describe`, cannot be reached or send
+/// schemas that cannot be decoded are not checked, so `None` is returned
+/// and the run goes on as usual.
+async fn describe_remote(
+    endpoint: &str,
+    test_id: &str,
+) -> Option<(Schema, Option<Schema>)> {
+    let mut client = transport::client(endpoint).await.ok()?;
+    client.require("Describe").ok()?;
+    let request = DescribeRequest { test_id: test_id.to_string() };
+    let response = client.describe(request).await.ok()?.into_inner();
+    let args = rmp_serde::from_slice(&response.args_schema).ok()?;
+    let return_value = match response.return_schema {
+        Some(schema) => Some(rmp_serde::from_slice(&schema).ok()?),
+        None => None,
+    };
+    Some((args, return_value))
+}
+
+/// Compares the schemas of the arguments sent to a runner and of the value
+/// it returns with what the receiving side expects, listing every mismatch.
+fn check_schemas(
+    args: Option<&Schema>,
+    runner_args: &Schema,
+    runner_return: Option<&Schema>,
+    return_value: &Schema,
+) -> Result<(), String> {
+    let mut sections = vec![];
+    let mut report = |heading: &str, found: Vec<String>| {
+        if !found.is_empty() {
+            sections.push(format!("{}\n  {}", heading, found.join("\n  ")));
+        }
+    };
+    if let Some(args) = args {
+        report(
+            "The runner cannot read the arguments:",
+            mismatches(args, runner_args),
+        );
+    }
+    if let Some(runner_return) = runner_return {
+        report(
+            "The runner's return value cannot be read:",
+            mismatches(runner_return, return_value),
+        );
+    }
+    if sections.is_empty() {
+        Ok(())
+    } else {
+        Err(sections.join("\n"))
+    }
+}
+
+/// Executes a remote test on several arguments in one `ExecuteBatch` call,
+/// without shrinking.
+///
+/// Runners that do not implement `ExecuteBatch` are sent one `Execute` call
+/// per case instead. Over a `Run` stream, the cases are sent one after
+/// another without waiting for their results. An `Err` means the runner
+/// could not be called at all.
+pub(crate) async fn execute_remote_batch<Rt: RemoteTest>(
+    test: &Rt,
+    args: &[Rt::Args],
+) -> Result<Vec<TestResult>, String> {
+    let (stream, mut client) = connect(test.endpoint()).await?;
+    let session_id =
+        run_session(&mut client, test.endpoint(), Rt::TEST_ID).await;
+    let codec = test.codec();
+    let mut requests = Vec::with_capacity(args.len());
+    for args in args {
+        requests.push(ExecuteRequest {
+            test_id: Rt::TEST_ID.to_string(),
+            test_data: codec.encode(args)?,
+            session_id: session_id.clone(),
+            encoding: codec.encoding().into(),
+            version: client.version(),
+        });
+    }
+    let responses = match stream {
+        Some(stream) => {
+            let cases =
+                requests.iter().map(|case| stream.execute(case.clone()));
+            try_join_all(cases).await?
+        }
+        None => execute_batch(&mut client, &requests).await?,
+    };
+    if responses.len() != requests.len() {
+        return Err(format!(
+            "Runner returned {} responses for a batch of {} cases",
+            responses.len(),
+            requests.len()
+        ));
+    }
+    for response in &responses {
+        client.check_response(response)?;
+    }
+    Ok(args
+        .iter()
+        .zip(requests)
+        .zip(responses)
+        .map(|((args, request), response)| {
+            to_test_result(args, request.test_data, response)
+        })
+        .collect())
+}
+
+/// Executes cases with one `ExecuteBatch` call, or one `Execute` call per
+/// case if the runner does not implement it or speaks protocol version 1.
+async fn execute_batch(
+    client: &mut transport::Client,
+    requests: &[ExecuteRequest],
+) -> Result<Vec<ExecuteResponse>, String> {
+    if client.extended() {
+        let batch = ExecuteBatchRequest { requests: requests.to_vec() };
+        match client.execute_batch(batch).await {
+            Ok(response) => return Ok(response.into_inner().responses),
+            Err(status) if status.code() == Code::Unimplemented => {}
+            Err(status) => return Err(status.to_string()),
+        }
+    }
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        let response = client
+            .execute(request.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        responses.push(response.into_inner());
+    }
+    Ok(responses)
+}
+
+/// Converts a runner's response to the result of the case it executed.
+pub(crate) fn to_test_result<A: Debug>(
+    args: &A,
+    test_data: Vec<u8>,
+    response: ExecuteResponse,
+) -> TestResult {
+    let proto_status = ProtoStatus::try_from(response.status)
+        .unwrap_or(ProtoStatus::Failed);
+    TestResult {
+        status: proto_status.into(),
+        arguments: vec![format!("{:?}", args)],
+        failure: if proto_status == ProtoStatus::Failed {
+            Some(TestFailure::Property(response.failure_detail))
+        } else { None },
+        return_value: response.return_value,
+        test_data: Some(test_data),
+        labels: response.labels,
+        tables: response
+            .tables
+            .into_iter()
+            .map(|entry| (entry.table, entry.value))
+            .collect(),
+        coverage: response
+            .coverage
+            .into_iter()
+            .map(|requirement| (requirement.label, requirement.percentage))
+            .collect(),
+        ..TestResult::default()
+    }
+}
+
+/// Produces the result of a case whose runner could not be called.
+fn call_failed<A: Serialize + Debug>(
+    codec: Codec,
+    args: &A,
+    e: String,
+) -> TestResult {
+    TestResult {
+        status: Fail,
+        arguments: vec![format!("{:?}", args)],
+        failure: Some(TestFailure::Runtime(format!("Tester failed to call runner: {}", e))),
+        return_value: None,
+        test_data: codec.encode(args).ok(),
+        ..TestResult::default()
+    }
+}
+
+/// Shrinks the arguments of a failing remote test, returning the smallest
+/// failure found, if any.
+async fn shrink_failure<Rt: RemoteTest>(
+    test: &Rt,
+    initial_args: Rt::Args,
+) -> Option<TestResult> {
+    println!("Shrinking... Args: {:?}", initial_args);
+    // Collect the iterator into a Vec to hold across await points
+    let shrunk_values: Vec<_> = initial_args.shrink().collect();
+    
+    for shrunk_args in shrunk_values {
+        match execute_remote(test, &shrunk_args).await {
+            Ok(new_result) => {
+                if new_result.is_failure() {
+                    let smaller_failure = Box::pin(shrink_failure(test, shrunk_args)).await;
+                    
+                    if let Some(smaller_result) = smaller_failure {
+                        return Some(smaller_result);
+                    } else {
+                        return Some(new_result);
+                    }
+                }
+            }
+            Err(e) => {
+                // A runtime error occurred during shrinking.
+                // This is a candidate for the smallest failure.
+                return Some(call_failed(test.codec(), &shrunk_args, e));
+            }
+        }
+    }
+    None
 }
 
 /// Implements `Testable` for any type that implements our new `RemoteTest` trait.
@@ -304,77 +1428,25 @@ where
     T: RemoteTest + 'static,
 {
     type Args = T::Args;
-    
-    async fn result(&self, args: &Self::Args) -> TestResult {
-        async fn execute_remote<Rt: RemoteTest>(
-            test: &Rt,
-            args: &Rt::Args,
-        ) -> Result<TestResult, String> {
-            let mut client =
-                TestRunnerClient::connect(test.endpoint().to_string())
-                    .await
-                    .map_err(|e| e.to_string())?;
-            let args_msgpack =
-                rmp_serde::to_vec_named(args).map_err(|e| e.to_string())?;
-            // println!("args_json: {:#?}", args_json);
-            let request = tonic::Request::new(ExecuteRequest {
-                test_id: Rt::TEST_ID.to_string(),
-                test_data: args_msgpack,
-            });
-            let response = client
-                .execute(request)
-                .await
-                .map_err(|e| e.to_string())?
-                .into_inner();
-            // println!("response: {:#?}", response);
-            let proto_status = ProtoStatus::try_from(response.status)
-                .unwrap_or(ProtoStatus::Failed);
-            Ok(TestResult {
-                status: proto_status.into(),
-                arguments: vec![format!("{:?}", args)],
-                failure: if proto_status == ProtoStatus::Failed {
-                    Some(TestFailure::Property(response.failure_detail))
-                } else { None },
-                return_value: response.return_value,
-            })
-        }
-
-        async fn shrink_failure<Rt: RemoteTest>(
-            test: &Rt,
-            initial_args: Rt::Args,
-        ) -> Option<TestResult> {
-            println!("Shrinking... Args: {:?}", initial_args);
-            // Collect the iterator into a Vec to hold across await points
-            let shrunk_values: Vec<_> = initial_args.shrink().collect();
-            
-            for shrunk_args in shrunk_values {
-                match execute_remote(test, &shrunk_args).await {
-                    Ok(new_result) => {
-                        if new_result.is_failure() {
-                            let smaller_failure = Box::pin(shrink_failure(test, shrunk_args)).await;
-                            
-                            if let Some(smaller_result) = smaller_failure {
-                                return Some(smaller_result);
-                            } else {
-                                return Some(new_result);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        // A runtime error occurred during shrinking.
-                        // This is a candidate for the smallest failure.
-                        return Some(TestResult {
-                            status: Fail,
-                            arguments: vec![format!("{:?}", shrunk_args)],
-                            failure: Some(TestFailure::Runtime(format!("Tester failed to call runner: {}", e))),
-                            return_value: None,
-                        });
-                    }
-                }
-            }
-            None
-        }
 
+    fn test_id(&self) -> Option<String> {
+        Some(T::TEST_ID.to_string())
+    }
+
+    fn replays_regressions(&self) -> bool {
+        self.args_decoder().is_some()
+    }
+
+    fn decode_args(&self, test_data: &[u8]) -> Option<Self::Args> {
+        self.args_decoder()?(Codec::MsgPack, test_data).ok()
+    }
+
+    fn corpus_entry(&self, test_data: &[u8]) -> Option<Vec<u8>> {
+        let args = self.args_decoder()?(self.codec(), test_data).ok()?;
+        Codec::MsgPack.encode(&args).ok()
+    }
+
+    async fn result(&self, args: &Self::Args) -> TestResult {
         match execute_remote(self, args).await {
             Ok(result) => {
                 if result.is_failure() {
@@ -384,12 +1456,44 @@ where
                     result
                 }
             }
-            Err(e) => TestResult {
-                status: Fail,
-                arguments: vec![format!("{:?}", args)],
-                failure: Some(TestFailure::Runtime(format!("Tester failed to call runner: {}", e))),
-                return_value: None,
-            },
+            Err(e) => call_failed(self.codec(), args, e),
+        }
+    }
+
+    async fn check_once(&self, args: &Self::Args) -> TestResult {
+        execute_remote(self, args)
+            .await
+            .unwrap_or_else(|e| call_failed(self.codec(), args, e))
+    }
+
+    async fn check_schema(&self) -> Result<(), String> {
+        match describe_remote(self.endpoint(), T::TEST_ID).await {
+            Some((args, return_value)) => check_schemas(
+                self.args_schema().as_ref(),
+                &args,
+                return_value.as_ref(),
+                &schema_of::<T::Return>(),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    async fn results(&self, args: &[Self::Args]) -> Vec<TestResult> {
+        if args.len() == 1 {
+            return vec![self.result(&args[0]).await];
+        }
+        let mut results = match execute_remote_batch(self, args).await {
+            Ok(results) => results,
+            Err(e) => return vec![call_failed(self.codec(), &args[0], e)],
+        };
+        // Only the first failure in generation order is shrunk and reported.
+        if let Some(i) = results.iter().position(TestResult::is_failure) {
+            results.truncate(i + 1);
+            let shrunk = shrink_failure(self, args[i].clone()).await;
+            if let Some(shrunk) = shrunk {
+                results[i] = shrunk;
+            }
         }
+        results
     }
 }
@@ -265,10 +265,30 @@
 //         true
 //     }
 // }
-use crate::{quickcheck, quickcheck_composite, tester::RemoteTest, Arbitrary, Gen};
+use crate::{
+    constant_size, linear_size, quickcheck, quickcheck_composite,
+    tester::{RemoteTest, TestFailure},
+    ArgsDecoder, Arbitrary, Codec, CompositeTest, Gen, ModelTest, QuickCheck,
+    RunnerGenerated, TestResult, Testable, Tls,
+};
+use quickcheck_rpc::test_runner_server::{TestRunner, TestRunnerServer};
+use quickcheck_rpc::{
+    BeginSessionRequest, BeginSessionResponse, DescribeRequest,
+    DescribeResponse, EndSessionRequest, EndSessionResponse,
+    ExecuteBatchRequest, ExecuteBatchResponse, ExecuteRequest,
+    ExecuteResponse, GenerateRequest, GenerateResponse, HandshakeRequest,
+    HandshakeResponse, RunRequest, ShrinkRequest, ShrinkResponse,
+};
+use quickcheck_runner::{schema_of, Schema, SingleTestRunner, TestFunction};
 use serde::{Serialize, Deserialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
 
 const ENDPOINT: &str = "http://[::1]:50051";
+const NODE_ENDPOINT: &str = "http://[::1]:50052";
 
 // 1. Define a struct for your test's arguments.
 //    It must derive Arbitrary, Serialize, and other traits.
@@ -358,6 +378,10 @@ impl RemoteTest for AddTest {
     type Return = i64;
     const TEST_ID: &'static str = "add_test";
     fn endpoint(&self) -> &str { &self.endpoint }
+
+    fn args_schema(&self) -> Option<Schema> {
+        Some(schema_of::<AddArgs>())
+    }
 }
 
 #[tokio::test]
@@ -375,6 +399,52 @@ async fn test_the_add_test() {
     });
 }
 
+#[tokio::test]
+#[ignore] // Run this test manually when the gRPC runner is active.
+async fn test_the_add_test_majority() {
+    let test1 = AddTest { endpoint: ENDPOINT.to_string() };
+    let test2 = AddTest { endpoint: ENDPOINT.to_string() };
+    let test3 = AddTest { endpoint: ENDPOINT.to_string() };
+    quickcheck_composite!(test1, test2, test3; majority);
+}
+
+#[tokio::test]
+#[ignore] // Run this test manually when the gRPC runner is active.
+async fn test_the_add_test_against_model() {
+    let test = AddTest { endpoint: ENDPOINT.to_string() };
+    quickcheck(ModelTest::new(test, |args: &AddArgs| {
+        args.a.wrapping_add(args.b)
+    }))
+    .await;
+}
+
+// --- Example comparing runners with different return types ---
+
+/// The Node.js runner's `add_test`, which returns a JavaScript number.
+struct NodeAddTest {
+    endpoint: String,
+}
+
+impl RemoteTest for NodeAddTest {
+    type Args = AddArgs;
+    type Return = f64;
+    const TEST_ID: &'static str = "add_test";
+    fn endpoint(&self) -> &str { &self.endpoint }
+}
+
+#[tokio::test]
+#[ignore] // Run this test manually when both gRPC runners are active.
+async fn test_rust_and_node_add() {
+    let rust = AddTest { endpoint: ENDPOINT.to_string() };
+    let node = NodeAddTest { endpoint: NODE_ENDPOINT.to_string() };
+    let composite = CompositeTest::comparing(|_args: &AddArgs, results| {
+        results[0] == results[1]
+    })
+    .implementation_with(rust, |x: i64| x as f64)
+    .implementation(node);
+    quickcheck(composite).await;
+}
+
 // --- Test panic handling ---
 struct PanicTest {
     endpoint: String,
@@ -397,3 +467,1032 @@ async fn test_panic_handling() {
     // quickcheck(test).await;
     quickcheck_composite!(test1, test2, |_args, _results| { false });
 }
+
+#[tokio::test]
+#[ignore] // Run this test manually when the gRPC runner is active.
+async fn test_panic_handling_outcomes() {
+    let test1 = PanicTest { endpoint: ENDPOINT.to_string() };
+    let test2 = PanicTest { endpoint: ENDPOINT.to_string() };
+    // Both runners fail on every input, which counts as agreement.
+    quickcheck(CompositeTest::new_outcomes(
+        vec![test1, test2],
+        crate::compare::same_outcome(crate::compare::all_equal()),
+    ))
+    .await;
+}
+
+// --- Size scaling ---
+
+/// Arguments that capture the size of the `Gen` they were generated with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SizeArgs(usize);
+
+impl Arbitrary for SizeArgs {
+    fn arbitrary(g: &mut Gen) -> Self {
+        SizeArgs(g.size())
+    }
+}
+
+/// Fails once the size reaches a threshold.
+struct SizeBelow(usize);
+
+#[async_trait::async_trait]
+impl Testable for SizeBelow {
+    type Args = SizeArgs;
+
+    async fn result(&self, args: &SizeArgs) -> TestResult {
+        TestResult::from_bool(args.0 < self.0)
+    }
+}
+
+#[test]
+fn linear_size_ramps() {
+    let sizes: Vec<usize> = (0..10).map(|n| linear_size(n, 10, 100)).collect();
+    assert_eq!(sizes, vec![10, 20, 30, 40, 50, 60, 70, 80, 90, 100]);
+    assert_eq!(linear_size(0, 100, 10), 1);
+    assert_eq!(linear_size(500, 100, 10), 10);
+    assert_eq!(constant_size(0, 100, 10), 10);
+}
+
+#[tokio::test]
+async fn size_is_reported() {
+    let mut qc = QuickCheck::new().set_rng(Gen::new(100)).tests(100);
+    let r = qc.quicktest(SizeBelow(50)).await.unwrap_err();
+    assert_eq!(r.size, Some(50));
+    assert!(r.failed_msg().contains("Size: 50."));
+
+    let mut qc = QuickCheck::new().set_rng(Gen::new(40)).tests(100);
+    assert_eq!(qc.quicktest(SizeBelow(50)).await.unwrap(), 100);
+
+    let mut qc =
+        QuickCheck::new().set_rng(Gen::new(100)).sizing(constant_size);
+    let r = qc.quicktest(SizeBelow(50)).await.unwrap_err();
+    assert_eq!(r.size, Some(100));
+}
+
+// --- Runner sessions ---
+
+/// Counts how often each lifecycle hook of a runner is called.
+#[derive(Default)]
+struct HookCounts {
+    setup_session: AtomicUsize,
+    teardown_session: AtomicUsize,
+    setup_case: AtomicUsize,
+    teardown_case: AtomicUsize,
+}
+
+struct CountingAdd(Arc<HookCounts>);
+
+impl TestFunction for CountingAdd {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        Ok(args.a.wrapping_add(args.b))
+    }
+
+    fn setup_session(&self) -> Result<(), String> {
+        self.0.setup_session.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn teardown_session(&self) {
+        self.0.teardown_session.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn setup_case(&self) -> Result<(), String> {
+        self.0.setup_case.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn teardown_case(&self) {
+        self.0.teardown_case.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn sessions_span_a_run() {
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().tests(20);
+    assert_eq!(qc.quicktest(test).await.unwrap(), 20);
+    assert_eq!(counts.setup_session.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.teardown_session.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.setup_case.load(Ordering::SeqCst), 20);
+    assert_eq!(counts.teardown_case.load(Ordering::SeqCst), 20);
+}
+
+// --- Batch execution ---
+
+#[tokio::test]
+async fn batches_stop_at_the_needed_number_of_tests() {
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().tests(25).batch_size(10);
+    assert_eq!(qc.quicktest(test).await.unwrap(), 25);
+    assert_eq!(counts.setup_session.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.setup_case.load(Ordering::SeqCst), 25);
+}
+
+/// An addition that rejects large first operands.
+struct SmallAdd;
+
+impl TestFunction for SmallAdd {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        if args.a > 10 {
+            return Err(format!("{} is too large", args.a));
+        }
+        Ok(args.a.wrapping_add(args.b))
+    }
+}
+
+#[tokio::test]
+async fn batched_failures_are_shrunk() {
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn("127.0.0.1:0").await.unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().batch_size(16);
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    assert!(r.size.is_some());
+}
+
+// --- Run streams ---
+
+#[tokio::test]
+async fn streams_carry_a_whole_run() {
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().tests(30).batch_size(4).streaming(true);
+    assert_eq!(qc.quicktest(test).await.unwrap(), 30);
+    assert_eq!(counts.setup_session.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.teardown_session.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.setup_case.load(Ordering::SeqCst), 30);
+}
+
+#[tokio::test]
+async fn streamed_failures_are_shrunk() {
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn("127.0.0.1:0").await.unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().streaming(true);
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+}
+
+// --- Schemas ---
+
+#[derive(Serialize, Deserialize)]
+struct LabelledArgs {
+    a: String,
+    b: i64,
+    c: bool,
+}
+
+/// A runner whose types disagree with `AddTest`'s.
+struct LabelledAdd;
+
+impl TestFunction for LabelledAdd {
+    type Args = LabelledArgs;
+    type Return = String;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: LabelledArgs) -> Result<String, String> {
+        Ok(format!("{}: {}", args.a, args.b))
+    }
+
+    fn return_schema(&self) -> Option<Schema> {
+        Some(schema_of::<String>())
+    }
+}
+
+#[tokio::test]
+async fn schema_mismatches_fail_before_running() {
+    let endpoint = SingleTestRunner::new(LabelledAdd)
+        .spawn("127.0.0.1:0")
+        .await
+        .unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Schema(
+            "The runner cannot read the arguments:\n  \
+             at top level: missing field `c`\n  \
+             at `a`: expected string, found integer\n\
+             The runner's return value cannot be read:\n  \
+             at top level: expected integer, found string"
+                .to_string()
+        ))
+    );
+    assert!(r.arguments.is_empty());
+}
+
+// --- Encodings ---
+
+/// `AddTest` with its arguments and return values encoded as JSON.
+struct JsonAddTest {
+    endpoint: String,
+}
+
+impl RemoteTest for JsonAddTest {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+    fn endpoint(&self) -> &str { &self.endpoint }
+    fn codec(&self) -> Codec { Codec::Json }
+
+    fn args_decoder(&self) -> Option<ArgsDecoder<AddArgs>> {
+        Some(|codec, test_data| codec.decode(test_data))
+    }
+}
+
+#[tokio::test]
+async fn runners_answer_in_the_requested_encoding() {
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn("127.0.0.1:0").await.unwrap();
+    let test = JsonAddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    assert!(r.test_data.unwrap().starts_with(br#"{"a":11,"b":"#));
+
+    let passing = AddArgs { a: 1, b: 2 };
+    let test = JsonAddTest { endpoint };
+    let r = test.result(&passing).await;
+    assert_eq!(r.return_value, Some(b"3".to_vec()));
+}
+
+#[tokio::test]
+async fn regressions_are_stored_as_message_pack_for_every_codec() {
+    let dir = std::env::temp_dir()
+        .join(format!("quickcheck-json-corpus-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let corpus = crate::RegressionCorpus::new(&dir);
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn("127.0.0.1:0").await.unwrap();
+
+    let test = JsonAddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new().regressions(Some(corpus.clone()));
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert!(r.test_data.unwrap().starts_with(br#"{"a":11,"b":"#));
+    let entries = corpus.load("add_test");
+    assert_eq!(entries.len(), 1);
+    let args: AddArgs = rmp_serde::from_slice(&entries[0]).unwrap();
+    assert_eq!(args.a, 11);
+
+    // The entry is replayed, and still fails, on the next run.
+    let test = JsonAddTest { endpoint };
+    let mut qc = QuickCheck::new()
+        .regressions(Some(corpus.clone()))
+        .tests(0)
+        .max_tests(0);
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    assert_eq!(corpus.load("add_test"), entries);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn remote_tests_without_a_decoder_keep_no_regressions() {
+    let dir = std::env::temp_dir()
+        .join(format!("quickcheck-no-corpus-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let corpus = crate::RegressionCorpus::new(&dir);
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn("127.0.0.1:0").await.unwrap();
+
+    let mut qc = QuickCheck::new().regressions(Some(corpus.clone()));
+    assert!(qc.quicktest(AddTest { endpoint }).await.is_err());
+    assert!(corpus.load("add_test").is_empty());
+    assert!(!dir.exists());
+}
+
+// --- Transports ---
+
+#[tokio::test]
+async fn runners_serve_unix_sockets() {
+    let path = std::env::temp_dir()
+        .join(format!("quickcheck-{}.sock", std::process::id()));
+    let endpoint = SingleTestRunner::new(SmallAdd)
+        .spawn(&format!("unix:{}", path.display()))
+        .await
+        .unwrap();
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn runners_only_replace_stale_sockets() {
+    let dir = std::env::temp_dir()
+        .join(format!("quickcheck-sockets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // A file that is not a socket is left alone.
+    let file = dir.join("not-a-socket");
+    std::fs::write(&file, "keep me").unwrap();
+    let address = format!("unix:{}", file.display());
+    let err = SingleTestRunner::new(SmallAdd)
+        .spawn(&address)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Cannot serve on {}: the path exists and is not a socket",
+            address
+        )
+    );
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+
+    // A socket left behind by an earlier runner is replaced.
+    let stale = dir.join("stale.sock");
+    drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+    let address = format!("unix:{}", stale.display());
+    let endpoint =
+        SingleTestRunner::new(SmallAdd).spawn(&address).await.unwrap();
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(AddTest { endpoint }).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn runners_that_cannot_be_spawned_are_reported() {
+    let test = AddTest { endpoint: "stdio:quickcheck-no-such-runner".into() };
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(test).await.unwrap_err();
+    match r.failure {
+        Some(TestFailure::Runtime(msg)) => assert!(
+            msg.contains("Failed to spawn runner `quickcheck-no-such-runner`"),
+            "{}",
+            msg
+        ),
+        failure => panic!("unexpected failure: {:?}", failure),
+    }
+}
+
+#[tokio::test]
+async fn rust_runners_run_in_process() {
+    let endpoint = SingleTestRunner::new(SmallAdd)
+        .spawn("inproc:small_add")
+        .await
+        .unwrap();
+    let small = AddTest { endpoint };
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let endpoint = runner.spawn("inproc:counting_add").await.unwrap();
+    let wrapping = AddTest { endpoint };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(small)
+        .implementation(wrapping);
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(composite).await.unwrap_err();
+    assert!(
+        format!("{:?}", r.failure).contains("11 is too large"),
+        "{:?}",
+        r.failure
+    );
+    assert!(counts.setup_session.load(Ordering::SeqCst) > 0);
+
+    let test = AddTest { endpoint: "inproc:no_such_runner".into() };
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Runtime(
+            "Tester failed to call runner: \
+             No in-process runner named `no_such_runner`"
+                .to_string()
+        ))
+    );
+}
+
+/// An addition that labels its cases by the sign of the first operand.
+struct SignedAdd;
+
+impl TestFunction for SignedAdd {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        Ok(args.a.wrapping_add(args.b))
+    }
+
+    fn labels(&self, args: &AddArgs) -> Vec<String> {
+        vec![if args.a < 0 { "negative" } else { "non-negative" }.into()]
+    }
+}
+
+/// An addition that requires cases of both signs, without labelling them.
+struct CoveredAdd(f64);
+
+impl TestFunction for CoveredAdd {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        Ok(args.a.wrapping_add(args.b))
+    }
+
+    fn tables(&self, args: &AddArgs) -> Vec<(String, String)> {
+        vec![("b".into(), args.b.signum().to_string())]
+    }
+
+    fn coverage(&self, _args: &AddArgs) -> Vec<(String, f64)> {
+        vec![("negative".into(), self.0), ("non-negative".into(), self.0)]
+    }
+}
+
+#[tokio::test]
+async fn composite_cases_carry_every_implementations_tags() {
+    let endpoint =
+        SingleTestRunner::new(SignedAdd).spawn("inproc:signed").await.unwrap();
+    let signed = AddTest { endpoint };
+    let endpoint = SingleTestRunner::new(CoveredAdd(10.0))
+        .spawn("inproc:covered")
+        .await
+        .unwrap();
+    let covered = AddTest { endpoint };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(signed)
+        .implementation(covered);
+    let mut qc = QuickCheck::new();
+    assert_eq!(qc.quicktest(composite).await.unwrap(), 100);
+    let coverage = qc.coverage();
+    assert_eq!(
+        coverage.count("negative") + coverage.count("non-negative"),
+        100
+    );
+    assert!(coverage.insufficient().is_empty());
+    assert!(coverage.report().contains("Table b"));
+
+    // A requirement from one runner fails the run if the labels of the
+    // other don't meet it.
+    let endpoint = SingleTestRunner::new(CoveredAdd(90.0))
+        .spawn("inproc:demanding")
+        .await
+        .unwrap();
+    let demanding = AddTest { endpoint };
+    let signed = AddTest { endpoint: "inproc:signed".into() };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(signed)
+        .implementation(demanding);
+    let r = qc.quicktest(composite).await.unwrap_err();
+    assert!(matches!(r.failure, Some(TestFailure::Coverage(_))));
+}
+
+/// An addition that is off by one once the first operand reaches 10.
+struct OffByOne(Arc<AtomicUsize>);
+
+impl TestFunction for OffByOne {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let sum = args.a.wrapping_add(args.b);
+        Ok(if args.a >= 10 { sum.wrapping_add(1) } else { sum })
+    }
+}
+
+#[tokio::test]
+async fn composite_failures_are_shrunk_as_a_whole() {
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let endpoint = runner.spawn("inproc:exact_add").await.unwrap();
+    let exact = AddTest { endpoint };
+    let calls = Arc::new(AtomicUsize::new(0));
+    let runner = SingleTestRunner::new(OffByOne(calls.clone()));
+    let endpoint = runner.spawn("inproc:off_by_one").await.unwrap();
+    let off_by_one = AddTest { endpoint };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(exact)
+        .implementation(off_by_one);
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(composite).await.unwrap_err();
+
+    // The reported case is the smallest disagreement, and the returns in
+    // the report are the ones of that case.
+    let args: AddArgs = rmp_serde::from_slice(&r.test_data.unwrap()).unwrap();
+    assert_eq!(args.a, 10);
+    assert_eq!(r.arguments, vec![format!("{:?}", args)]);
+    let report = match r.failure {
+        Some(TestFailure::Comparison(report)) => report,
+        failure => panic!("unexpected failure: {:?}", failure),
+    };
+    let sum = args.a.wrapping_add(args.b);
+    assert_eq!(report.returns[0].value, sum.to_string());
+    assert_eq!(report.returns[1].value, sum.wrapping_add(1).to_string());
+
+    // Every case and shrink step ran each implementation exactly once.
+    assert_eq!(
+        counts.setup_case.load(Ordering::SeqCst),
+        calls.load(Ordering::SeqCst)
+    );
+}
+
+/// An addition that returns its sum as a string.
+struct StringAdd;
+
+impl TestFunction for StringAdd {
+    type Args = AddArgs;
+    type Return = String;
+    const TEST_ID: &'static str = "string_add";
+
+    fn execute(&self, args: AddArgs) -> Result<String, String> {
+        Ok(args.a.wrapping_add(args.b).to_string())
+    }
+}
+
+/// `StringAdd`, as seen by the tester.
+struct StringAddTest {
+    endpoint: String,
+}
+
+impl RemoteTest for StringAddTest {
+    type Args = AddArgs;
+    type Return = String;
+    const TEST_ID: &'static str = "string_add";
+    fn endpoint(&self) -> &str { &self.endpoint }
+}
+
+#[tokio::test]
+async fn composites_compare_differently_typed_implementations() {
+    let endpoint = SingleTestRunner::new(StringAdd)
+        .spawn("inproc:string_add")
+        .await
+        .unwrap();
+    let strings = StringAddTest { endpoint: endpoint.clone() };
+    let runner = SingleTestRunner::new(SignedAdd);
+    let integers = AddTest {
+        endpoint: runner.spawn("inproc:integer_add").await.unwrap(),
+    };
+    let composite = CompositeTest::comparing(
+        |_args: &AddArgs, results: &[String]| results[0] == results[1],
+    )
+    .implementation_with(integers, |sum: i64| sum.to_string())
+    .implementation(strings);
+    let mut qc = QuickCheck::new();
+    assert_eq!(qc.quicktest(composite).await.unwrap(), 100);
+
+    // Disagreements are reported in the common type.
+    let calls = Arc::new(AtomicUsize::new(0));
+    let runner = SingleTestRunner::new(OffByOne(calls));
+    let off_by_one = AddTest {
+        endpoint: runner.spawn("inproc:off_by_one_string").await.unwrap(),
+    };
+    let strings = StringAddTest { endpoint };
+    let composite = CompositeTest::comparing(
+        |_args: &AddArgs, results: &[String]| results[0] == results[1],
+    )
+    .implementation_with(off_by_one, |sum: i64| sum.to_string())
+    .implementation(strings);
+    let r = qc.quicktest(composite).await.unwrap_err();
+    let args: AddArgs = rmp_serde::from_slice(&r.test_data.unwrap()).unwrap();
+    let report = match r.failure {
+        Some(TestFailure::Comparison(report)) => report,
+        failure => panic!("unexpected failure: {:?}", failure),
+    };
+    let sum = args.a.wrapping_add(args.b);
+    let expected = format!("{:?}", sum.wrapping_add(1).to_string());
+    assert_eq!(report.returns[0].value, expected);
+    assert_eq!(report.returns[1].test_id, "string_add");
+    assert_eq!(report.returns[1].value, format!("{:?}", sum.to_string()));
+}
+
+/// An addition that only returns once the other implementation of the same
+/// case has been called too, so that calling implementations one after
+/// another times out.
+struct Rendezvous(Arc<AtomicUsize>);
+
+impl TestFunction for Rendezvous {
+    type Args = AddArgs;
+    type Return = i64;
+    const TEST_ID: &'static str = "add_test";
+
+    fn execute(&self, args: AddArgs) -> Result<i64, String> {
+        // Calls 2n and 2n + 1 belong to the same case.
+        let call = self.0.fetch_add(1, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while self.0.load(Ordering::SeqCst) <= (call | 1) {
+            if Instant::now() > deadline {
+                return Err("the other implementation was not called".into());
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Ok(args.a.wrapping_add(args.b))
+    }
+}
+
+/// Serves a runner on a thread and runtime of its own, so that its test
+/// function can block without holding up the tester or other runners.
+fn spawn_on_thread<F: TestFunction>(
+    runner: SingleTestRunner<F>,
+    address: &'static str,
+) -> String {
+    let (endpoint, spawned) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            endpoint.send(runner.spawn(address).await.unwrap()).unwrap();
+            std::future::pending::<()>().await
+        })
+    });
+    spawned.recv().unwrap()
+}
+
+#[tokio::test]
+async fn implementations_are_called_concurrently() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let first = AddTest {
+        endpoint: spawn_on_thread(
+            SingleTestRunner::new(Rendezvous(calls.clone())),
+            "inproc:rendezvous_first",
+        ),
+    };
+    let second = AddTest {
+        endpoint: spawn_on_thread(
+            SingleTestRunner::new(Rendezvous(calls.clone())),
+            "inproc:rendezvous_second",
+        ),
+    };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(first)
+        .implementation(second);
+    let mut qc = QuickCheck::new().tests(10);
+    assert_eq!(qc.quicktest(composite).await.unwrap(), 10);
+    assert_eq!(calls.load(Ordering::SeqCst), 20);
+}
+
+#[tokio::test]
+async fn failing_implementations_do_not_affect_the_others() {
+    let counts = Arc::new(HookCounts::default());
+    let runner = SingleTestRunner::new(CountingAdd(counts.clone()));
+    let counting = AddTest {
+        endpoint: runner.spawn("inproc:isolated_counting").await.unwrap(),
+    };
+    let small = AddTest {
+        endpoint: SingleTestRunner::new(SmallAdd)
+            .spawn("inproc:isolated_small")
+            .await
+            .unwrap(),
+    };
+    let composite =
+        CompositeTest::comparing(|_args: &AddArgs, results: &[i64]| {
+            results[0] == results[1]
+        })
+        .implementation(small)
+        .implementation(counting);
+    let mut qc = QuickCheck::new();
+    let r = qc.quicktest(composite).await.unwrap_err();
+
+    // The failure is the failing implementation's own, on the composite's
+    // smallest case, and was not shrunk on its own.
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+    let args: AddArgs = rmp_serde::from_slice(&r.test_data.unwrap()).unwrap();
+    assert_eq!(args.a, 11);
+
+    // The other implementation still ran every case, in a session of its
+    // own that was ended.
+    assert!(counts.setup_case.load(Ordering::SeqCst) > 0);
+    assert_eq!(
+        counts.setup_case.load(Ordering::SeqCst),
+        counts.teardown_case.load(Ordering::SeqCst)
+    );
+    assert_eq!(
+        counts.setup_session.load(Ordering::SeqCst),
+        counts.teardown_session.load(Ordering::SeqCst)
+    );
+}
+
+#[tokio::test]
+async fn majorities_flag_dissenters_by_name() {
+    let runner = SingleTestRunner::new(SignedAdd);
+    let first = AddTest {
+        endpoint: runner.spawn("inproc:majority_first").await.unwrap(),
+    };
+    let runner = SingleTestRunner::new(SignedAdd);
+    let second = AddTest {
+        endpoint: runner.spawn("inproc:majority_second").await.unwrap(),
+    };
+    let calls = Arc::new(AtomicUsize::new(0));
+    let runner = SingleTestRunner::new(OffByOne(calls));
+    let odd = AddTest {
+        endpoint: runner.spawn("inproc:majority_odd").await.unwrap(),
+    };
+    quickcheck_composite!(first, second, odd; majority);
+
+    let tests = ["first", "second", "odd"].map(|name| AddTest {
+        endpoint: format!("inproc:majority_{}", name),
+    });
+    let majority = crate::compare::majority();
+    let composite = CompositeTest::new(Vec::from(tests), majority)
+        .flag_dissenters();
+    let mut qc = QuickCheck::new();
+    assert_eq!(qc.quicktest(composite).await.unwrap(), 100);
+    let coverage = qc.coverage();
+    assert!(coverage.count("dissent: add_test @ inproc:majority_odd") > 0);
+    assert_eq!(coverage.count("dissent: add_test @ inproc:majority_first"), 0);
+
+    // Other comparisons accept cases without flagging anyone.
+    let tests = ["first", "second", "odd"].map(|name| AddTest {
+        endpoint: format!("inproc:majority_{}", name),
+    });
+    let close = crate::compare::all_equal_by(|a: &i64, b: &i64| {
+        a.wrapping_sub(*b).unsigned_abs() <= 1
+    });
+    let composite = CompositeTest::new(Vec::from(tests), close);
+    let mut qc = QuickCheck::new();
+    assert_eq!(qc.quicktest(composite).await.unwrap(), 100);
+    let coverage = qc.coverage();
+    assert_eq!(coverage.count("dissent: add_test @ inproc:majority_odd"), 0);
+}
+
+// --- Security ---
+
+/// Issues a certificate for `name`, signed by `ca`, and writes it and its key
+/// to `dir` as `NAME.pem` and `NAME.key`.
+fn issue(
+    dir: &std::path::Path,
+    name: &str,
+    ca: &rcgen::Issuer<'_, rcgen::KeyPair>,
+) {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let params = rcgen::CertificateParams::new(vec![name.into()]).unwrap();
+    let cert = params.signed_by(&key, ca).unwrap();
+    std::fs::write(dir.join(format!("{}.pem", name)), cert.pem()).unwrap();
+    std::fs::write(dir.join(format!("{}.key", name)), key.serialize_pem())
+        .unwrap();
+}
+
+#[tokio::test]
+async fn runners_require_client_certificates_and_tokens() {
+    let dir = std::env::temp_dir()
+        .join(format!("quickcheck-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut params = rcgen::CertificateParams::new(vec![]).unwrap();
+    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca = rcgen::CertifiedIssuer::self_signed(
+        params,
+        rcgen::KeyPair::generate().unwrap(),
+    )
+    .unwrap();
+    std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+    issue(&dir, "localhost", &ca);
+    issue(&dir, "tester", &ca);
+
+    let runner = SingleTestRunner::new(SmallAdd)
+        .tls(
+            Tls::new()
+                .identity(dir.join("localhost.pem"), dir.join("localhost.key"))
+                .ca_cert(dir.join("ca.pem")),
+        )
+        .token("s3cret");
+    let endpoint = runner.spawn("127.0.0.1:0").await.unwrap();
+    let tls = Tls::new().ca_cert(dir.join("ca.pem")).domain_name("localhost");
+    let client_tls =
+        tls.clone().identity(dir.join("tester.pem"), dir.join("tester.key"));
+    // The files are read when the settings are built, not on every call.
+    std::fs::remove_dir_all(&dir).unwrap();
+    let runtime_error = |r: TestResult| match r.failure {
+        Some(TestFailure::Runtime(msg)) => msg,
+        failure => panic!("unexpected failure: {:?}", failure),
+    };
+
+    let test = AddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new().tls(client_tls.clone()).token("s3cret");
+    let r = qc.quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Property(Some("11 is too large".to_string())))
+    );
+
+    // Without a client certificate, the handshake fails.
+    let test = AddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new().tls(tls).token("s3cret");
+    let msg = runtime_error(qc.quicktest(test).await.unwrap_err());
+    assert!(msg.starts_with("Tester failed to call runner"), "{}", msg);
+
+    let test = AddTest { endpoint };
+    let mut qc = QuickCheck::new().tls(client_tls);
+    let msg = runtime_error(qc.quicktest(test).await.unwrap_err());
+    assert!(msg.contains("Missing token"), "{}", msg);
+}
+
+/// A runner that predates `Handshake`, so speaks protocol version 1 and only
+/// implements `Execute`. It records every other RPC it is sent.
+#[derive(Clone, Default)]
+struct VersionOne {
+    handshakes: Arc<AtomicUsize>,
+    executed: Arc<AtomicUsize>,
+    unexpected: Arc<std::sync::Mutex<Vec<&'static str>>>,
+}
+
+impl VersionOne {
+    fn unexpected<T>(&self, rpc: &'static str) -> Result<Response<T>, Status> {
+        self.unexpected.lock().unwrap().push(rpc);
+        Err(Status::unimplemented(rpc))
+    }
+}
+
+#[tonic::async_trait]
+impl TestRunner for VersionOne {
+    type RunStream = quickcheck_runner::RunStream;
+
+    async fn handshake(
+        &self,
+        _: Request<HandshakeRequest>,
+    ) -> Result<Response<HandshakeResponse>, Status> {
+        self.handshakes.fetch_add(1, Ordering::SeqCst);
+        Err(Status::unimplemented("Handshake"))
+    }
+
+    async fn execute(
+        &self,
+        _: Request<ExecuteRequest>,
+    ) -> Result<Response<ExecuteResponse>, Status> {
+        self.executed.fetch_add(1, Ordering::SeqCst);
+        Ok(Response::new(ExecuteResponse {
+            return_value: Some(rmp_serde::to_vec(&0i64).unwrap()),
+            ..ExecuteResponse::default()
+        }))
+    }
+
+    async fn execute_batch(
+        &self,
+        _: Request<ExecuteBatchRequest>,
+    ) -> Result<Response<ExecuteBatchResponse>, Status> {
+        self.unexpected("ExecuteBatch")
+    }
+
+    async fn run(
+        &self,
+        _: Request<tonic::Streaming<RunRequest>>,
+    ) -> Result<Response<Self::RunStream>, Status> {
+        self.unexpected("Run")
+    }
+
+    async fn generate(
+        &self,
+        _: Request<GenerateRequest>,
+    ) -> Result<Response<GenerateResponse>, Status> {
+        self.unexpected("Generate")
+    }
+
+    async fn shrink(
+        &self,
+        _: Request<ShrinkRequest>,
+    ) -> Result<Response<ShrinkResponse>, Status> {
+        self.unexpected("Shrink")
+    }
+
+    async fn describe(
+        &self,
+        _: Request<DescribeRequest>,
+    ) -> Result<Response<DescribeResponse>, Status> {
+        self.unexpected("Describe")
+    }
+
+    async fn begin_session(
+        &self,
+        _: Request<BeginSessionRequest>,
+    ) -> Result<Response<BeginSessionResponse>, Status> {
+        self.unexpected("BeginSession")
+    }
+
+    async fn end_session(
+        &self,
+        _: Request<EndSessionRequest>,
+    ) -> Result<Response<EndSessionResponse>, Status> {
+        self.unexpected("EndSession")
+    }
+}
+
+#[tokio::test]
+async fn version_one_runners_are_only_sent_execute() {
+    let runner = VersionOne::default();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(TestRunnerServer::new(runner.clone()))
+            .serve_with_incoming(TcpIncoming::from(listener)),
+    );
+
+    let test = AddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new().tests(10).batch_size(5);
+    assert_eq!(qc.quicktest(test).await.unwrap(), 10);
+    let test = AddTest { endpoint: endpoint.clone() };
+    let mut qc = QuickCheck::new().tests(10).streaming(true);
+    assert_eq!(qc.quicktest(test).await.unwrap(), 10);
+    assert_eq!(runner.executed.load(Ordering::SeqCst), 20);
+
+    let test = RunnerGenerated::new("add_test", endpoint.clone());
+    let r = QuickCheck::new().quicktest(test).await.unwrap_err();
+    assert_eq!(
+        r.failure,
+        Some(TestFailure::Runtime(
+            "Tester failed to call runner: Runner speaks protocol version 1, \
+             which has no Generate"
+                .to_string()
+        ))
+    );
+    assert!(runner.unexpected.lock().unwrap().is_empty());
+
+    // Outside of a run, the version is negotiated once for every call.
+    let handshakes = runner.handshakes.load(Ordering::SeqCst);
+    let test = AddTest { endpoint };
+    let args = AddArgs { a: 1, b: 2 };
+    assert!(!test.check_once(&args).await.is_failure());
+    assert!(!test.check_once(&args).await.is_failure());
+    assert_eq!(runner.handshakes.load(Ordering::SeqCst), handshakes + 1);
+}
+
+#[tokio::test]
+async fn runners_reject_requests_in_versions_they_do_not_speak() {
+    let endpoint = SingleTestRunner::new(SignedAdd)
+        .spawn("inproc:future_versions")
+        .await
+        .unwrap();
+    let channel = quickcheck_rpc::transport::channel(&endpoint, None)
+        .await
+        .unwrap();
+    let mut client =
+        quickcheck_rpc::test_runner_client::TestRunnerClient::new(channel);
+    let request = ExecuteRequest {
+        test_id: "add_test".to_string(),
+        test_data: rmp_serde::to_vec_named(&AddArgs { a: 1, b: 2 }).unwrap(),
+        ..ExecuteRequest::default()
+    };
+
+    let response = client.execute(request.clone()).await.unwrap();
+    assert_eq!(response.get_ref().version, 1);
+    let current = ExecuteRequest { version: 2, ..request.clone() };
+    let response = client.execute(current).await.unwrap();
+    assert_eq!(response.get_ref().version, 2);
+
+    let future = ExecuteRequest { version: 9, ..request };
+    let status = client.execute(future).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(
+        status.message(),
+        "Request uses protocol version 9, but this runner speaks 1 to 2"
+    );
+}
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use tonic::Code;
+
+use quickcheck_rpc::security::{SendToken, Tls};
+use quickcheck_rpc::test_runner_client::TestRunnerClient;
+use quickcheck_rpc::{transport, version, ExecuteResponse};
+
+/// A client of a runner, which sends the run's token with every request.
+///
+/// It derefs to the generated client, and knows the protocol version
+/// negotiated with the runner, so that RPCs the runner doesn't speak are
+/// never called.
+#[derive(Clone)]
+pub(crate) struct Client {
+    inner: Inner,
+    version: u32,
+}
+
+/// The generated client wrapped by `Client`.
+type Inner = TestRunnerClient<InterceptedService<Channel, SendToken>>;
+
+impl Client {
+    /// The protocol version negotiated with the runner, which `Execute`
+    /// requests are sent with.
+    pub(crate) fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Fails unless the runner answered an `Execute` request in the version
+    /// it was sent with.
+    pub(crate) fn check_response(
+        &self,
+        response: &ExecuteResponse,
+    ) -> Result<(), String> {
+        version::check_response(self.version, response.version)
+    }
+
+    /// Whether the runner speaks the RPCs added after `Execute`, which are
+    /// `ExecuteBatch`, `Run`, `Generate`, `Shrink`, `Describe` and sessions.
+    pub(crate) fn extended(&self) -> bool {
+        self.version >= version::EXTENSIONS_VERSION
+    }
+
+    /// Fails unless the runner speaks `rpc`, one of the RPCs added after
+    /// `Execute`.
+    pub(crate) fn require(&self, rpc: &str) -> Result<(), String> {
+        if self.extended() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Runner speaks protocol version {}, which has no {}",
+                self.version, rpc
+            ))
+        }
+    }
+}
+
+impl Deref for Client {
+    type Target = Inner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Client {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// How a tester secures its connections to runners.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Security {
+    /// Used for `https` endpoints.
+    pub(crate) tls: Option<Tls>,
+    pub(crate) token: Option<String>,
+}
+
+/// The channels to runners opened during a run, keyed by endpoint.
+///
+/// For `stdio:` endpoints, a channel also keeps its runner process alive.
+pub(crate) type Channels = Arc<tokio::sync::Mutex<HashMap<String, Channel>>>;
+
+/// The protocol versions negotiated with runners during a run, keyed by
+/// endpoint.
+pub(crate) type Versions = Arc<tokio::sync::Mutex<HashMap<String, u32>>>;
+
+/// The channels to runners opened outside of a run, keyed by endpoint.
+///
+/// `stdio:` endpoints are left out, so that their processes are killed once
+/// their last client is dropped.
+static CHANNELS_OUTSIDE_RUNS: Mutex<BTreeMap<String, Channel>> =
+    Mutex::new(BTreeMap::new());
+
+/// The protocol versions negotiated with runners outside of a run, keyed by
+/// endpoint.
+///
+/// A runner restarted at the same endpoint that no longer speaks the cached
+/// version rejects requests sent with it, instead of misreading them.
+static VERSIONS_OUTSIDE_RUNS: Mutex<BTreeMap<String, u32>> =
+    Mutex::new(BTreeMap::new());
+
+tokio::task_local! {
+    /// The channels to runners of the run in progress.
+    pub(crate) static CHANNELS: Channels;
+
+    /// The security settings of the run in progress.
+    pub(crate) static SECURITY: Security;
+
+    /// The protocol versions of the run in progress.
+    pub(crate) static VERSIONS: Versions;
+}
+
+/// Connects to the runner at `endpoint`, which is any endpoint
+/// `quickcheck_rpc::transport::channel` accepts.
+///
+/// Within a run, each endpoint is connected to once and the channel is
+/// shared by all of the run's calls, so a `stdio:` runner is spawned once per
+/// run. Outside of a run, channels are shared by all calls made outside of
+/// runs, except that every `stdio:` client gets a process of its own. A
+/// runner process is killed once its last client is dropped.
+///
+/// Within a run, the client uses the run's TLS settings for `https`
+/// endpoints, and sends the run's token to every runner. The protocol
+/// version is negotiated with each runner once per run, and once for all
+/// calls made outside of runs; runners the tester cannot speak to are an
+/// error. Callers check `Client::extended` before calling RPCs added after
+/// `Execute`.
+pub(crate) async fn client(endpoint: &str) -> Result<Client, String> {
+    let security = SECURITY.try_with(Security::clone).unwrap_or_default();
+    let channel = match CHANNELS.try_with(Arc::clone) {
+        Ok(channels) => {
+            let mut channels = channels.lock().await;
+            match channels.get(endpoint) {
+                Some(channel) => channel.clone(),
+                None => {
+                    let channel =
+                        transport::channel(endpoint, security.tls.as_ref())
+                            .await?;
+                    channels.insert(endpoint.to_string(), channel.clone());
+                    channel
+                }
+            }
+        }
+        Err(_) if endpoint.starts_with("stdio:") => {
+            transport::channel(endpoint, None).await?
+        }
+        Err(_) => {
+            let shared =
+                CHANNELS_OUTSIDE_RUNS.lock().unwrap().get(endpoint).cloned();
+            match shared {
+                Some(channel) => channel,
+                None => {
+                    let channel = transport::channel(endpoint, None).await?;
+                    CHANNELS_OUTSIDE_RUNS
+                        .lock()
+                        .unwrap()
+                        .insert(endpoint.to_string(), channel.clone());
+                    channel
+                }
+            }
+        }
+    };
+    let token = SendToken::new(security.token.as_deref())?;
+    let mut inner = TestRunnerClient::with_interceptor(channel, token);
+    let version = handshake(&mut inner, endpoint).await?;
+    Ok(Client { inner, version })
+}
+
+/// Negotiates the protocol version to speak with a runner.
+async fn handshake(
+    client: &mut Inner,
+    endpoint: &str,
+) -> Result<u32, String> {
+    let versions = VERSIONS.try_with(Arc::clone).ok();
+    let cached = match &versions {
+        Some(versions) => versions.lock().await.get(endpoint).copied(),
+        None => VERSIONS_OUTSIDE_RUNS.lock().unwrap().get(endpoint).copied(),
+    };
+    if let Some(version) = cached {
+        return Ok(version);
+    }
+    let version = match client.handshake(version::request()).await {
+        Ok(response) => version::accept(response.get_ref())?,
+        Err(status) if status.code() == Code::Unimplemented => {
+            version::accept_unimplemented()?
+        }
+        Err(status) => return Err(status.to_string()),
+    };
+    match versions {
+        Some(versions) => {
+            versions.lock().await.insert(endpoint.to_string(), version);
+        }
+        None => {
+            VERSIONS_OUTSIDE_RUNS
+                .lock()
+                .unwrap()
+                .insert(endpoint.to_string(), version);
+        }
+    }
+    Ok(version)
+}